@@ -0,0 +1,61 @@
+use libschedule24::data;
+
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::lesson;
+
+/// Renders the lessons of one or more days as plain text, one block per day
+/// with the time, subject, teacher and room of every lesson. Gaps between
+/// lessons are listed as breaks.
+pub fn render(days: &[(NaiveDate, &[data::LessonInfo])]) -> String {
+    let mut out = String::new();
+
+    for (i, (date, lesson_info)) in days.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("{}\n", date.format("%A %Y-%m-%d")));
+
+        let mut lessons: Vec<(NaiveTime, NaiveTime, [String; 3])> = lesson_info.iter()
+            .filter_map(|lesson| {
+                let (start, end) = lesson::times_or_warn(lesson)?;
+                Some((start, end, [0, 1, 2].map(|i| lesson::text(lesson, i))))
+            })
+            .collect();
+        lessons.sort_by_key(|l| (l.0, l.1));
+
+        if lessons.is_empty() {
+            out.push_str("  No lessons\n");
+            continue;
+        }
+
+        let widths = [0, 1].map(|i| lessons.iter().map(|l| l.2[i].chars().count()).max().unwrap_or(0));
+
+        // Lessons can overlap when a class is split in groups, so a break
+        // only starts after the latest end so far
+        let mut latest_end: Option<NaiveTime> = None;
+        for (start, end, [subject, teacher, room]) in &lessons {
+            if let Some(latest_end) = latest_end {
+                if latest_end < *start {
+                    out.push_str(&format!("  {}-{}  Break ({} min)\n", latest_end.format("%H:%M"), start.format("%H:%M"), start.signed_duration_since(latest_end).num_minutes()));
+                }
+            }
+            latest_end = Some(latest_end.map_or(*end, |latest_end| latest_end.max(*end)));
+
+            let line = format!(
+                "  {}-{}  {:subject_width$}  {:teacher_width$}  {}",
+                start.format("%H:%M"),
+                end.format("%H:%M"),
+                subject,
+                teacher,
+                room,
+                subject_width = widths[0],
+                teacher_width = widths[1],
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+
+    out
+}
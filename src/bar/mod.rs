@@ -0,0 +1,118 @@
+use libschedule24::data;
+
+use chrono::NaiveTime;
+use substring::Substring;
+
+use crate::lesson::{self, format_duration};
+
+pub const MODES: [&str; 4] = ["plain", "polybar", "waybar", "i3blocks"];
+
+pub struct BarState {
+    pub text: String,
+    pub short_text: String,
+    pub tooltip: String,
+    pub class: &'static str,
+    pub percentage: u32,
+    pub color: Option<String>,
+}
+
+/// Works out what to show from the lessons returned by `get_next_lesson_info`.
+pub fn state(lesson_info: &[data::LessonInfo], next_day: bool, now: NaiveTime) -> BarState {
+    let mut lessons: Vec<(NaiveTime, NaiveTime, &data::LessonInfo)> = lesson_info.iter()
+        .filter_map(|lesson| {
+            let (start, end) = lesson::times(lesson).ok()?;
+            Some((start, end, lesson))
+        })
+        .collect();
+    lessons.sort_by_key(|l| l.0);
+
+    let tooltip = lessons.iter()
+        .map(|(start, end, lesson)| format!("{}-{} {}", start.format("%H:%M"), end.format("%H:%M"), [subject(lesson), lesson::details(lesson, " ")].join(" ").trim()))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    if next_day {
+        return match lessons.first() {
+            Some((start, _, lesson)) => BarState {
+                text: format!("Next day: {} {}", subject(lesson), start.format("%H:%M")),
+                short_text: format!("{} {}", subject(lesson), start.format("%H:%M")),
+                tooltip,
+                class: "free",
+                percentage: 0,
+                color: Some(lesson::hex(lesson::color(lesson))),
+            },
+            None => empty(),
+        };
+    }
+
+    let next = lessons.iter().find(|(start, _, _)| *start > now);
+    if let Some((start, end, lesson)) = lessons.iter().find(|(start, end, _)| *start <= now && now < *end) {
+        let elapsed = now.signed_duration_since(*start).num_seconds();
+        let duration = end.signed_duration_since(*start).num_seconds().max(1);
+        let mut text = format!("{} {} left", subject(lesson), format_duration(end.signed_duration_since(now)));
+        if let Some((next_start, _, next_lesson)) = next {
+            text.push_str(&format!(" | {} {}", subject(next_lesson), next_start.format("%H:%M")));
+        }
+        return BarState {
+            text,
+            short_text: format!("{} {}", subject(lesson), format_duration(end.signed_duration_since(now))),
+            tooltip,
+            class: "lesson",
+            percentage: (elapsed * 100 / duration) as u32,
+            color: Some(lesson::hex(lesson::color(lesson))),
+        };
+    }
+
+    match next {
+        Some((start, _, lesson)) => BarState {
+            text: format!("Next: {} {} (in {})", subject(lesson), start.format("%H:%M"), format_duration(start.signed_duration_since(now))),
+            short_text: format!("{} {}", subject(lesson), format_duration(start.signed_duration_since(now))),
+            tooltip,
+            class: "break",
+            percentage: 0,
+            color: Some(lesson::hex(lesson::color(lesson))),
+        },
+        None => empty(),
+    }
+}
+
+pub fn render(mode: &str, state: &BarState) -> String {
+    match mode {
+        "waybar" => serde_json::json!({
+            "text": state.text,
+            "tooltip": state.tooltip,
+            "class": state.class,
+            "percentage": state.percentage,
+        }).to_string(),
+        "i3blocks" => {
+            let mut object = serde_json::json!({
+                "full_text": state.text,
+                "short_text": state.short_text,
+            });
+            if let Some(color) = &state.color {
+                object["color"] = serde_json::Value::String(color.substring(0, 7).to_string());
+            }
+            object.to_string()
+        },
+        "polybar" => match &state.color {
+            Some(color) => format!("%{{F{}}}●%{{F-}} {}", color.substring(0, 7), state.text),
+            None => state.text.clone(),
+        },
+        _ => state.text.clone(),
+    }
+}
+
+fn empty() -> BarState {
+    BarState {
+        text: String::new(),
+        short_text: String::new(),
+        tooltip: String::new(),
+        class: "none",
+        percentage: 0,
+        color: None,
+    }
+}
+
+fn subject(lesson: &data::LessonInfo) -> String {
+    lesson::text(lesson, 0)
+}
@@ -0,0 +1,271 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
+
+// Offsets like +2w are limited to about a hundred years
+const MAX_OFFSET_DAYS: i64 = 36_525;
+
+/// Parses a date given on the command line, relative to `today`.
+///
+/// Accepts ISO dates (`2021-11-24`), ISO weeks (`2021-W47`, `2021-W47-3`),
+/// `today`, `tomorrow`, `yesterday`, weekday names optionally prefixed with
+/// `next` or `last`, `this week`, `next week`, `last week` and offsets like
+/// `+2w`, `-3d`.
+pub fn parse(input: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let input = input.trim().to_lowercase();
+    let words: Vec<&str> = input.split_whitespace().collect();
+
+    match &words[..] {
+        ["today"] => return Ok(today),
+        ["tomorrow"] => return Ok(today + Duration::days(1)),
+        ["yesterday"] => return Ok(today - Duration::days(1)),
+        ["this", "week"] => return Ok(monday(today)),
+        ["next", "week"] => return Ok(monday(today) + Duration::weeks(1)),
+        ["last", "week"] => return Ok(monday(today) - Duration::weeks(1)),
+        [day] if weekday(day).is_some() => {
+            let days = (weekday(day).unwrap().num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64).rem_euclid(7);
+            return Ok(today + Duration::days(days));
+        },
+        ["next", day] if weekday(day).is_some() => {
+            let days = (weekday(day).unwrap().num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64 - 1).rem_euclid(7) + 1;
+            return Ok(today + Duration::days(days));
+        },
+        ["last", day] if weekday(day).is_some() => {
+            let days = (today.weekday().num_days_from_monday() as i64 - weekday(day).unwrap().num_days_from_monday() as i64 - 1).rem_euclid(7) + 1;
+            return Ok(today - Duration::days(days));
+        },
+        _ => {},
+    }
+
+    if let Some(days) = relative(&input) {
+        return Some(days)
+            .filter(|days| days.abs() <= MAX_OFFSET_DAYS)
+            .and_then(|days| today.checked_add_signed(Duration::days(days)))
+            .ok_or_else(|| format!("The date \"{}\" is too far away", input));
+    }
+    if let Some(date) = iso_week(&input) {
+        return Ok(date);
+    }
+    NaiveDate::parse_from_str(&input, "%Y-%m-%d").map_err(|_| format!("Could not understand the date \"{}\"", input))
+}
+
+/// An ISO week together with the ISO week year it belongs to, since the week
+/// number alone is ambiguous around new year.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Week {
+    pub year: i32,
+    pub week: i32,
+}
+
+impl Week {
+    /// Checks that the week exists, only some years have a week 53.
+    pub fn new(year: i32, week: i32) -> Option<Week> {
+        NaiveDate::from_isoywd_opt(year, week as u32, Weekday::Mon).map(|_| Week { year, week })
+    }
+
+    pub fn of(date: NaiveDate) -> Week {
+        let week = date.iso_week();
+        Week { year: week.year(), week: week.week() as i32 }
+    }
+
+    pub fn current() -> Week {
+        Week::of(Local::now().naive_local().date())
+    }
+
+    pub fn monday(self) -> NaiveDate {
+        NaiveDate::from_isoywd(self.year, self.week as u32, Weekday::Mon)
+    }
+
+    /// The week `weeks` weeks later, or earlier when negative.
+    pub fn offset(self, weeks: i64) -> Week {
+        Week::of(self.monday() + Duration::weeks(weeks))
+    }
+
+    /// The date of a day in this week, where 1 is monday.
+    pub fn day(self, day: i32) -> Option<NaiveDate> {
+        NaiveDate::from_isoywd_opt(self.year, self.week as u32, weekday_from_number(day as u32)?)
+    }
+}
+
+/// Every week that has at least one day between `from` and `to`, inclusive.
+pub fn weeks(from: NaiveDate, to: NaiveDate) -> Vec<Week> {
+    let mut weeks = Vec::new();
+    let mut week = Week::of(from);
+    while week.monday() <= to {
+        weeks.push(week);
+        week = week.offset(1);
+    }
+    weeks
+}
+
+impl std::fmt::Display for Week {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-W{:02}", self.year, self.week)
+    }
+}
+
+/// Day of week (1 is monday) and ISO week of a date.
+pub fn selection(date: NaiveDate) -> (i32, Week) {
+    (date.weekday().number_from_monday() as i32, Week::of(date))
+}
+
+/// Weekends have no lessons, so they select the following monday.
+pub fn school_day(date: NaiveDate) -> NaiveDate {
+    if date.weekday().number_from_monday() > 5 {
+        monday(date) + Duration::weeks(1)
+    } else {
+        date
+    }
+}
+
+/// The first school day after `date`.
+pub fn next_school_day(date: NaiveDate) -> NaiveDate {
+    school_day(date + Duration::days(1))
+}
+
+pub fn monday(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+// +2w, -3d, 1w as a number of days
+fn relative(input: &str) -> Option<i64> {
+    let (sign, rest) = match input.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, input.strip_prefix('+').unwrap_or(input)),
+    };
+    if rest.len() < 2 {
+        return None;
+    }
+    let (n, unit) = rest.split_at(rest.len() - 1);
+    let n: i64 = n.parse().ok()?;
+    let days = match unit {
+        "d" => n,
+        "w" => n.checked_mul(7)?,
+        _ => return None,
+    };
+    days.checked_mul(sign)
+}
+
+// 2021-w47 or 2021-w47-3
+fn iso_week(input: &str) -> Option<NaiveDate> {
+    let mut parts = input.split('-');
+    let year: i32 = parts.next()?.parse().ok()?;
+    let week: u32 = parts.next()?.strip_prefix('w')?.parse().ok()?;
+    let day = match parts.next() {
+        Some(day) => weekday_from_number(day.parse().ok()?)?,
+        None => Weekday::Mon,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    NaiveDate::from_isoywd_opt(year, week, day)
+}
+
+pub fn weekday_from_number(n: u32) -> Option<Weekday> {
+    match n {
+        1 => Some(Weekday::Mon),
+        2 => Some(Weekday::Tue),
+        3 => Some(Weekday::Wed),
+        4 => Some(Weekday::Thu),
+        5 => Some(Weekday::Fri),
+        6 => Some(Weekday::Sat),
+        7 => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(y, m, d)
+    }
+
+    #[test]
+    fn friday_of_week_52_continues_in_week_1() {
+        // 2021 has 52 weeks, friday 2021-12-31 is in week 52
+        let friday = date(2021, 12, 31);
+        assert_eq!(Week::of(friday), Week { year: 2021, week: 52 });
+
+        let next = next_school_day(friday);
+        assert_eq!(next, date(2022, 1, 3));
+        assert_eq!(selection(next), (1, Week { year: 2022, week: 1 }));
+    }
+
+    #[test]
+    fn friday_of_week_52_continues_in_week_53() {
+        // 2020 has 53 weeks
+        let next = next_school_day(date(2020, 12, 25));
+        assert_eq!(selection(next), (1, Week { year: 2020, week: 53 }));
+    }
+
+    #[test]
+    fn friday_of_week_53_belongs_to_previous_year() {
+        // friday 2021-01-01 is in week 53 of 2020
+        let friday = date(2021, 1, 1);
+        assert_eq!(selection(friday), (5, Week { year: 2020, week: 53 }));
+
+        let next = next_school_day(friday);
+        assert_eq!(selection(next), (1, Week { year: 2021, week: 1 }));
+    }
+
+    #[test]
+    fn weekend_selects_monday_of_next_week() {
+        assert_eq!(selection(school_day(date(2021, 1, 2))), (1, Week { year: 2021, week: 1 }));
+        assert_eq!(selection(school_day(date(2022, 1, 1))), (1, Week { year: 2022, week: 1 }));
+    }
+
+    #[test]
+    fn weeks_between_dates() {
+        assert_eq!(weeks(date(2020, 12, 23), date(2021, 1, 4)), vec![
+            Week { year: 2020, week: 52 },
+            Week { year: 2020, week: 53 },
+            Week { year: 2021, week: 1 },
+        ]);
+        assert!(weeks(date(2021, 1, 4), date(2021, 1, 3)).is_empty());
+    }
+
+    #[test]
+    fn week_53_only_in_long_years() {
+        assert_eq!(Week::new(2020, 53), Some(Week { year: 2020, week: 53 }));
+        assert_eq!(Week::new(2021, 53), None);
+        assert_eq!(Week::new(2021, 0), None);
+    }
+
+    #[test]
+    fn week_offset_crosses_years() {
+        assert_eq!(Week { year: 2020, week: 53 }.offset(1), Week { year: 2021, week: 1 });
+        assert_eq!(Week { year: 2021, week: 52 }.offset(1), Week { year: 2022, week: 1 });
+        assert_eq!(Week { year: 2021, week: 1 }.offset(-1), Week { year: 2020, week: 53 });
+        assert_eq!(Week { year: 2022, week: 1 }.offset(-1), Week { year: 2021, week: 52 });
+    }
+
+    #[test]
+    fn iso_week_dates_parse_with_year() {
+        let today = date(2021, 12, 31);
+        assert_eq!(parse("2020-W53-5", today), Ok(date(2021, 1, 1)));
+        assert_eq!(parse("+1w", today).map(Week::of), Ok(Week { year: 2022, week: 1 }));
+        assert_eq!(parse("next monday", today), Ok(date(2022, 1, 3)));
+    }
+
+    #[test]
+    fn huge_offsets_are_errors() {
+        let today = date(2021, 12, 31);
+        assert_eq!(parse("-3d", today), Ok(date(2021, 12, 28)));
+        assert!(parse("+100000000w", today).is_err());
+        assert!(parse("+99999999999999999d", today).is_err());
+        assert!(parse("-9223372036854775807w", today).is_err());
+    }
+}
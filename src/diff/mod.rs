@@ -0,0 +1,103 @@
+use libschedule24::data;
+
+use substring::Substring;
+
+use crate::lesson::{self, text};
+
+pub enum Change {
+    Added(data::LessonInfo),
+    Removed(data::LessonInfo),
+    Moved(data::LessonInfo, data::LessonInfo),
+    RoomChanged(data::LessonInfo, data::LessonInfo),
+}
+
+/// Compares two versions of the same schedule.
+///
+/// Lessons in the same slot with the same subject but other texts count as a
+/// room change, and a lesson with the same subject and teacher in another
+/// slot counts as moved. Everything left over was added or removed.
+pub fn diff(old: &[data::LessonInfo], new: &[data::LessonInfo]) -> Vec<Change> {
+    let mut old: Vec<data::LessonInfo> = old.to_vec();
+    let mut new: Vec<data::LessonInfo> = new.to_vec();
+    let mut changes = Vec::new();
+
+    old.retain(|o| {
+        match new.iter().position(|n| same_slot(o, n) && o.texts == n.texts) {
+            Some(i) => {
+                new.remove(i);
+                false
+            },
+            None => true,
+        }
+    });
+
+    old.retain(|o| {
+        match new.iter().position(|n| same_slot(o, n) && text(o, 0) == text(n, 0)) {
+            Some(i) => {
+                changes.push(Change::RoomChanged(o.clone(), new.remove(i)));
+                false
+            },
+            None => true,
+        }
+    });
+
+    old.retain(|o| {
+        match new.iter().position(|n| text(o, 0) == text(n, 0) && text(o, 1) == text(n, 1)) {
+            Some(i) => {
+                changes.push(Change::Moved(o.clone(), new.remove(i)));
+                false
+            },
+            None => true,
+        }
+    });
+
+    changes.extend(old.into_iter().map(Change::Removed));
+    changes.extend(new.into_iter().map(Change::Added));
+    changes
+}
+
+impl Change {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Change::Added(_) => "added",
+            Change::Removed(_) => "removed",
+            Change::Moved(_, _) => "moved",
+            Change::RoomChanged(_, _) => "changed",
+        }
+    }
+
+    /// The lesson before and after the change.
+    pub fn lessons(&self) -> (Option<&data::LessonInfo>, Option<&data::LessonInfo>) {
+        match self {
+            Change::Added(lesson) => (None, Some(lesson)),
+            Change::Removed(lesson) => (Some(lesson), None),
+            Change::Moved(old, new) | Change::RoomChanged(old, new) => (Some(old), Some(new)),
+        }
+    }
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added(lesson) => write!(f, "+ Added:   {} {} ({})", slot(lesson), text(lesson, 0), details(lesson)),
+            Change::Removed(lesson) => write!(f, "- Removed: {} {} ({})", slot(lesson), text(lesson, 0), details(lesson)),
+            Change::Moved(old, new) => write!(f, "~ Moved:   {} {} -> {}", text(old, 0), slot(old), slot(new)),
+            Change::RoomChanged(old, new) => write!(f, "~ Changed: {} {} ({}) -> ({})", slot(old), text(old, 0), details(old), details(new)),
+        }
+    }
+}
+
+fn same_slot(a: &data::LessonInfo, b: &data::LessonInfo) -> bool {
+    a.day_of_week_number == b.day_of_week_number
+        && a.time_start == b.time_start
+        && a.time_end == b.time_end
+}
+
+fn slot(lesson: &data::LessonInfo) -> String {
+    let day = lesson::day_name(lesson.day_of_week_number as usize).map_or("?", |name| &name[..3]);
+    format!("{} {}-{}", day, lesson.time_start.to_string().substring(0, 5), lesson.time_end.to_string().substring(0, 5))
+}
+
+fn details(lesson: &data::LessonInfo) -> String {
+    lesson::details(lesson, ", ")
+}
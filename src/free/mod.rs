@@ -0,0 +1,78 @@
+use libschedule24::data;
+
+use chrono::{Duration, NaiveTime};
+
+use crate::lesson;
+
+/// Time windows between `day_start` and `day_end` where none of the given
+/// lesson lists has a lesson, at least `min_duration` long.
+///
+/// Without explicit bounds the day runs from the first lesson start to the
+/// last lesson end over all lists, so only gaps during the school day count.
+pub fn free_slots(lesson_info: &[&[data::LessonInfo]], day_start: Option<NaiveTime>, day_end: Option<NaiveTime>, min_duration: Duration) -> Vec<(NaiveTime, NaiveTime)> {
+    let mut busy: Vec<(NaiveTime, NaiveTime)> = Vec::new();
+    for lessons in lesson_info {
+        busy.extend(lessons.iter().filter_map(lesson::times_or_warn));
+    }
+    free_between(busy, day_start, day_end, min_duration)
+}
+
+fn free_between(mut busy: Vec<(NaiveTime, NaiveTime)>, day_start: Option<NaiveTime>, day_end: Option<NaiveTime>, min_duration: Duration) -> Vec<(NaiveTime, NaiveTime)> {
+    busy.sort();
+
+    let day_start = match day_start.or_else(|| busy.first().map(|b| b.0)) {
+        Some(time) => time,
+        None => return Vec::new(),
+    };
+    let day_end = match day_end.or_else(|| busy.iter().map(|b| b.1).max()) {
+        Some(time) => time,
+        None => return Vec::new(),
+    };
+
+    let mut slots = Vec::new();
+    let mut free_from = day_start;
+    for (time_start, time_end) in busy {
+        if time_start > free_from {
+            push_slot(&mut slots, free_from, time_start.min(day_end), min_duration);
+        }
+        if time_end > free_from {
+            free_from = time_end;
+        }
+    }
+    push_slot(&mut slots, free_from, day_end, min_duration);
+
+    slots
+}
+
+fn push_slot(slots: &mut Vec<(NaiveTime, NaiveTime)>, from: NaiveTime, to: NaiveTime, min_duration: Duration) {
+    if to > from && to.signed_duration_since(from) >= min_duration {
+        slots.push((from, to));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms(h, m, 0)
+    }
+
+    #[test]
+    fn gaps_between_overlapping_lessons() {
+        let busy = vec![(time(10, 0), time(11, 0)), (time(8, 0), time(9, 0)), (time(8, 30), time(9, 30))];
+        assert_eq!(free_between(busy.clone(), None, None, Duration::zero()), vec![(time(9, 30), time(10, 0))]);
+        assert_eq!(free_between(busy.clone(), None, None, Duration::minutes(45)), vec![]);
+        assert_eq!(free_between(busy, Some(time(7, 0)), Some(time(12, 0)), Duration::zero()), vec![
+            (time(7, 0), time(8, 0)),
+            (time(9, 30), time(10, 0)),
+            (time(11, 0), time(12, 0)),
+        ]);
+    }
+
+    #[test]
+    fn no_lessons() {
+        assert_eq!(free_slots(&[&[]], None, None, Duration::zero()), vec![]);
+        assert_eq!(free_slots(&[&[]], Some(time(8, 0)), Some(time(16, 0)), Duration::zero()), vec![(time(8, 0), time(16, 0))]);
+    }
+}
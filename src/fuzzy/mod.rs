@@ -0,0 +1,97 @@
+/// Case-insensitive match score of `query` against `candidate`, higher is
+/// better. Exact matches beat prefixes, prefixes beat substrings and
+/// substrings beat scattered subsequences.
+pub fn score(query: &str, candidate: &str) -> Option<i32> {
+    let query = query.to_lowercase();
+    let candidate = candidate.to_lowercase();
+    let len_diff = candidate.chars().count() as i32 - query.chars().count() as i32;
+
+    if candidate == query {
+        Some(4000)
+    } else if candidate.starts_with(&query) {
+        Some(3000 - len_diff)
+    } else if candidate.contains(&query) {
+        Some(2000 - len_diff)
+    } else {
+        // every character of the query in order, fewer skipped characters first
+        let mut chars = candidate.chars();
+        let mut skipped = 0;
+        for q in query.chars() {
+            loop {
+                match chars.next() {
+                    Some(c) if c == q => break,
+                    Some(_) => skipped += 1,
+                    None => return None,
+                }
+            }
+        }
+        Some(1000 - skipped)
+    }
+}
+
+/// Candidates matching `query`, best match first.
+pub fn search<'a>(query: &str, candidates: &'a [String]) -> Vec<&'a String> {
+    let mut matches: Vec<(i32, &String)> = candidates.iter()
+        .filter_map(|c| score(query, c).map(|s| (s, c)))
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    matches.into_iter().map(|(_, c)| c).collect()
+}
+
+pub enum Resolution {
+    Found(String),
+    Ambiguous(Vec<String>),
+    NoMatch(Vec<String>),
+}
+
+/// Resolves a name typed by the user against the known names.
+///
+/// A case-insensitive exact match or a unique case-insensitive prefix is
+/// accepted, otherwise the closest candidates are returned as suggestions.
+pub fn resolve(query: &str, candidates: &[String]) -> Resolution {
+    if let Some(c) = candidates.iter().find(|c| *c == query) {
+        return Resolution::Found(c.clone());
+    }
+
+    let lower = query.to_lowercase();
+    let exact: Vec<&String> = candidates.iter().filter(|c| c.to_lowercase() == lower).collect();
+    if exact.len() == 1 {
+        return Resolution::Found(exact[0].clone());
+    }
+
+    let prefixed: Vec<&String> = candidates.iter().filter(|c| c.to_lowercase().starts_with(&lower)).collect();
+    match prefixed.len() {
+        0 => {},
+        1 => return Resolution::Found(prefixed[0].clone()),
+        _ => return Resolution::Ambiguous(prefixed.into_iter().take(10).cloned().collect()),
+    }
+
+    Resolution::NoMatch(closest(query, candidates, 5))
+}
+
+/// The `n` candidates with the smallest edit distance to `query`.
+pub fn closest(query: &str, candidates: &[String], n: usize) -> Vec<String> {
+    let lower = query.to_lowercase();
+    let mut distances: Vec<(usize, &String)> = candidates.iter()
+        .map(|c| (distance(&lower, &c.to_lowercase()), c))
+        .collect();
+    distances.sort();
+    distances.into_iter().take(n).map(|(_, c)| c.clone()).collect()
+}
+
+// Levenshtein distance
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,120 @@
+use libschedule24::data;
+
+use chrono::{Local, NaiveDate, NaiveTime};
+
+use crate::{date::{self, Week}, lesson};
+
+const PRODID: &str = "-//NAHFE//schedule24//EN";
+
+/// Builds an RFC 5545 calendar with one VEVENT per lesson.
+///
+/// `weeks` holds the lessons of every week that should be part of the
+/// calendar. `uid_seed` is mixed into every UID so that two
+/// classes exporting the same lesson slot do not collide.
+pub fn generate(uid_seed: &str, weeks: &[(Week, Vec<data::LessonInfo>)]) -> String {
+    let mut lines: Vec<String> = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+
+    let stamp = Local::now().naive_utc().format("%Y%m%dT%H%M%SZ").to_string();
+
+    for (week, lesson_info) in weeks {
+        for lesson in lesson_info {
+            let weekday = match date::weekday_from_number(lesson.day_of_week_number as u32) {
+                Some(weekday) => weekday,
+                None => continue,
+            };
+            let date = NaiveDate::from_isoywd(week.year, week.week as u32, weekday);
+            let (time_start, time_end) = match lesson::times_or_warn(lesson) {
+                Some(times) => times,
+                None => continue,
+            };
+
+            let summary = lesson::text(lesson, 0);
+            let teacher = lesson::text(lesson, 1);
+            let room = lesson::text(lesson, 2);
+            let description = lesson::details(lesson, "\n");
+
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}", uid(uid_seed, date, (time_start, time_end), lesson)));
+            lines.push(format!("DTSTAMP:{}", stamp));
+            lines.push(format!("DTSTART:{}", date.and_time(time_start).format("%Y%m%dT%H%M%S")));
+            lines.push(format!("DTEND:{}", date.and_time(time_end).format("%Y%m%dT%H%M%S")));
+            lines.push(format!("SUMMARY:{}", escape(&summary)));
+            if !room.is_empty() {
+                lines.push(format!("LOCATION:{}", escape(&room)));
+            }
+            if !teacher.is_empty() {
+                lines.push(format!("X-SCHEDULE24-TEACHER:{}", escape(&teacher)));
+            }
+            if !description.is_empty() {
+                lines.push(format!("DESCRIPTION:{}", escape(&description)));
+            }
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut calendar = String::new();
+    for line in lines {
+        calendar.push_str(&fold(&line));
+        calendar.push_str("\r\n");
+    }
+    calendar
+}
+
+/// UIDs only depend on the class, the lesson slot and the texts, so
+/// re-exporting the same week produces the same UIDs and calendar apps update
+/// events in place instead of duplicating them. Group lessons in the same slot
+/// differ in teacher or room, so they get UIDs of their own.
+fn uid(seed: &str, date: NaiveDate, (time_start, time_end): (NaiveTime, NaiveTime), lesson: &data::LessonInfo) -> String {
+    let end = time_end.format("%H%M%S").to_string();
+    let texts: Vec<String> = lesson.texts.iter().map(|t| t.to_string()).collect();
+
+    // FNV-1a, stable across builds unlike std's DefaultHasher. Every part
+    // ends with a zero byte so that the parts can't run into each other
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in [seed, &end].iter().copied().chain(texts.iter().map(|t| t.as_str())) {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{}-{:016x}@{}", date.and_time(time_start).format("%Y%m%dT%H%M%S"), hash, env!("CARGO_PKG_NAME"))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {},
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Content lines may not be longer than 75 octets, longer lines are split
+/// and continued with a leading space.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+    folded
+}
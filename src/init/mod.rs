@@ -0,0 +1,107 @@
+use libschedule24::{RequestError, get_schools, get_classes, get_school_guid};
+
+use std::io::{self, Write};
+
+use crate::{fuzzy, Config, Profile};
+
+/// Asks for the domain, school and class and writes them to the config file,
+/// either as the top level selection or as the named `profile`.
+pub async fn run(profile: Option<&str>, should_cache: bool) -> Result<Profile, RequestError> {
+    let mut cfg: Config = confy::load(env!("CARGO_PKG_NAME")).unwrap();
+    let current = cfg.profile(profile);
+
+    let domain = loop {
+        let domain = if current.domain.is_empty() {
+            prompt("Skola24 domain (like example.skola24.se): ")?
+        } else {
+            let domain = prompt(&format!("Skola24 domain [{}]: ", current.domain))?;
+            if domain.is_empty() { current.domain.clone() } else { domain }
+        };
+        if !domain.is_empty() {
+            break domain;
+        }
+    };
+
+    let schools: Vec<String> = get_schools(&domain, should_cache).await?.iter().map(|s| s.unit_id.to_string()).collect();
+    let school = choose("school", &schools)?;
+
+    let school_guid = get_school_guid(&domain, &school, should_cache).await?;
+    let classes: Vec<String> = get_classes(&domain, &school_guid, should_cache).await?.iter().map(|c| c.group_name.to_string()).collect();
+    let class = choose("class", &classes)?;
+
+    let new = Profile { domain, school, class };
+    match profile {
+        Some(name) => {
+            cfg.profiles.insert(name.to_string(), new.clone());
+            if cfg.default_profile.is_empty() {
+                cfg.default_profile = name.to_string();
+            }
+        },
+        None => {
+            cfg.domain = new.domain.clone();
+            cfg.school = new.school.clone();
+            cfg.class = new.class.clone();
+        },
+    }
+
+    confy::store(env!("CARGO_PKG_NAME"), cfg).map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e.to_string()))?;
+    println!("Saved {} / {} / {} to the config file", new.domain, new.school, new.class);
+
+    Ok(new)
+}
+
+pub fn confirm(question: &str) -> io::Result<bool> {
+    let answer = prompt(&format!("{} [Y/n] ", question))?.to_lowercase();
+    Ok(answer.is_empty() || answer == "y" || answer == "yes")
+}
+
+fn prompt(question: &str) -> io::Result<String> {
+    print!("{}", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No input"));
+    }
+    Ok(answer.trim().to_string())
+}
+
+fn choose(what: &str, candidates: &[String]) -> io::Result<String> {
+    if candidates.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("No {} to choose from", what)));
+    }
+
+    let mut query = prompt(&format!("Search {} (empty lists all): ", what))?;
+    loop {
+        let matches = if query.is_empty() {
+            candidates.iter().collect()
+        } else {
+            fuzzy::search(&query, candidates)
+        };
+
+        match matches.len() {
+            0 => {
+                query = prompt(&format!("No {} matches \"{}\", search again: ", what, query))?;
+                continue;
+            },
+            1 => {
+                println!("Selected {}", matches[0]);
+                return Ok(matches[0].clone());
+            },
+            _ => {},
+        }
+
+        for (i, candidate) in matches.iter().take(20).enumerate() {
+            println!("{:>3}. {}", i + 1, candidate);
+        }
+        if matches.len() > 20 {
+            println!("     ... and {} more", matches.len() - 20);
+        }
+
+        let answer = prompt(&format!("Pick a {} by number, or search again: ", what))?;
+        match answer.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= matches.len().min(20) => return Ok(matches[n - 1].clone()),
+            _ => query = answer,
+        }
+    }
+}
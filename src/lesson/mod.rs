@@ -0,0 +1,147 @@
+use std::{error::Error, fmt};
+
+use libschedule24::data;
+
+use chrono::{Duration, NaiveTime};
+
+/// Names of the school days, monday is 1 in `day_of_week_number`.
+pub const DAY_NAMES: [&str; 5] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday"];
+
+/// Colour used for lessons that don't have a valid colour.
+pub const DEFAULT_COLOR: (u8, u8, u8) = (0xcc, 0xcc, 0xcc);
+
+/// A field of a `LessonInfo` that could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Time(String),
+    Color(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Time(time) => write!(f, "Invalid time \"{}\"", time),
+            ParseError::Color(color) => write!(f, "Invalid colour \"{}\"", color),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parses `HH:MM:SS` as sent by Skola24, or `HH:MM`.
+pub fn parse_time(time: &str) -> Result<NaiveTime, ParseError> {
+    let trimmed = time.trim();
+    NaiveTime::parse_from_str(trimmed, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(trimmed, "%H:%M"))
+        .map_err(|_| ParseError::Time(time.to_string()))
+}
+
+/// Parses `#rrggbb` or `#rgb`.
+pub fn parse_color(color: &str) -> Result<(u8, u8, u8), ParseError> {
+    let error = || ParseError::Color(color.to_string());
+    let hex = color.trim().strip_prefix('#').ok_or_else(error)?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error());
+    }
+
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| error());
+    match hex.len() {
+        6 => Ok((channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+        3 => Ok((channel(&hex[0..1])? * 0x11, channel(&hex[1..2])? * 0x11, channel(&hex[2..3])? * 0x11)),
+        _ => Err(error()),
+    }
+}
+
+pub fn start(lesson: &data::LessonInfo) -> Result<NaiveTime, ParseError> {
+    parse_time(&lesson.time_start.to_string())
+}
+
+pub fn end(lesson: &data::LessonInfo) -> Result<NaiveTime, ParseError> {
+    parse_time(&lesson.time_end.to_string())
+}
+
+/// Start and end of a lesson, a lesson that ends before it starts is invalid.
+pub fn times(lesson: &data::LessonInfo) -> Result<(NaiveTime, NaiveTime), ParseError> {
+    let (start, end) = (start(lesson)?, end(lesson)?);
+    if end < start {
+        return Err(ParseError::Time(lesson.time_end.to_string()));
+    }
+    Ok((start, end))
+}
+
+/// Like `times`, but malformed lessons give a warning and `None` so that
+/// they can be skipped.
+pub fn times_or_warn(lesson: &data::LessonInfo) -> Option<(NaiveTime, NaiveTime)> {
+    match times(lesson) {
+        Ok(times) => Some(times),
+        Err(e) => {
+            eprintln!("Skipping {} on {}: {}", text(lesson, 0), day_name(lesson.day_of_week_number as usize).unwrap_or("an unknown day"), e);
+            None
+        },
+    }
+}
+
+/// The colour of a lesson, or `DEFAULT_COLOR` when it is malformed.
+pub fn color(lesson: &data::LessonInfo) -> (u8, u8, u8) {
+    parse_color(&lesson.block.b_color.to_string()).unwrap_or(DEFAULT_COLOR)
+}
+
+/// One of the texts of a lesson, the subject is 0, the teacher 1 and the
+/// room 2. Missing texts are empty.
+pub fn text(lesson: &data::LessonInfo, i: usize) -> String {
+    lesson.texts.get(i).map(|t| t.to_string()).unwrap_or_default()
+}
+
+/// `#rrggbb` for a colour from `color`.
+pub fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Rounded up to whole minutes, like `1h 05m` or `12m`.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = (duration.num_seconds() + 59) / 60;
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Every text after the subject that isn't empty, joined by `separator`.
+pub fn details(lesson: &data::LessonInfo, separator: &str) -> String {
+    lesson.texts.iter().skip(1).map(|t| t.to_string()).filter(|t| !t.is_empty()).collect::<Vec<String>>().join(separator)
+}
+
+pub fn day_name(day: usize) -> Option<&'static str> {
+    DAY_NAMES.get(day.wrapping_sub(1)).copied()
+}
+
+/// The lessons with valid times, malformed lessons are left out.
+pub fn valid(lesson_info: &[data::LessonInfo]) -> Vec<data::LessonInfo> {
+    lesson_info.iter().filter(|lesson| times(lesson).is_ok()).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("08:15:00"), Ok(NaiveTime::from_hms(8, 15, 0)));
+        assert_eq!(parse_time(" 08:15 "), Ok(NaiveTime::from_hms(8, 15, 0)));
+        assert_eq!(parse_time("8.15"), Err(ParseError::Time("8.15".to_string())));
+        assert!(parse_time("25:00:00").is_err());
+        assert!(parse_time("").is_err());
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#ff8000"), Ok((255, 128, 0)));
+        assert_eq!(parse_color("#F80"), Ok((255, 136, 0)));
+        assert_eq!(parse_color("ff8000"), Err(ParseError::Color("ff8000".to_string())));
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("#gg8000").is_err());
+        assert!(parse_color("#ffé00").is_err());
+        assert!(parse_color("").is_err());
+    }
+}
@@ -1,91 +1,180 @@
 mod stui;
+mod ics;
+mod free;
+mod store;
+mod diff;
+mod output;
+mod bar;
+mod fuzzy;
+mod init;
+mod date;
+mod agenda;
+mod page;
+mod png;
+mod wallpaper;
+mod theme;
+mod lesson;
 
-use libschedule24::{data, image, Dimensions, RequestError, get_schema, get_schools, get_classes, print_lessons, get_class_guid, get_school_guid, get_lesson_info};
-use std::{convert::TryInto, fs::File, io::Write};
-use chrono::{Local, Datelike, NaiveTime};
-use clap::{App, AppSettings, Arg, SubCommand, crate_authors, crate_description, crate_name, crate_version};
+use libschedule24::{data, image, Dimensions, RequestError, get_schools, get_classes, print_lessons, get_school_guid};
+use std::{collections::{BTreeMap, HashSet}, fs::File, io::{self, Write}};
+use crossterm::tty::IsTty;
+use date::Week;
+use chrono::{Local, NaiveDate, NaiveTime, Duration};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand, crate_authors, crate_description, crate_name, crate_version};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Config {
     domain: String,
     school: String,
     class: String,
     cache: bool,
+    default_profile: String,
+    profiles: BTreeMap<String, Profile>,
+    theme: theme::Theme,
 }
 
-impl ::std::default::Default for Config {
-    fn default() -> Self {
-        Self {
-            domain: String::new(),
-            school: String::new(),
-            class: String::new(),
-            cache: false,
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Profile {
+    domain: String,
+    school: String,
+    class: String,
+}
+
+impl Config {
+    // The top level domain, school and class are used when no profile is selected
+    fn profile(&self, name: Option<&str>) -> Profile {
+        let name = name.unwrap_or(&self.default_profile);
+        match self.profiles.get(name) {
+            Some(profile) => profile.clone(),
+            None => Profile {
+                domain: self.domain.clone(),
+                school: self.school.clone(),
+                class: self.class.clone(),
+            },
         }
     }
 }
 
 #[tokio::main]
 async fn main() {
-    if run_commands().await.is_ok() {}
+    if run_commands().await.is_err() {
+        if let Some(message) = store::error_message() {
+            eprintln!("{}", message);
+        }
+    }
 }
 
 async fn run_commands() -> Result<(), RequestError> {
     let cfg: Config = confy::load(env!("CARGO_PKG_NAME")).unwrap();
-    if cfg.domain.is_empty() || cfg.school.is_empty() || cfg.class.is_empty() {
-        println!("You should probably add the domain, school and class to the config file which should be at $XDG_CONFIG_HOME/{0}/{0}.toml", env!("CARGO_PKG_NAME"));
-    }
+    let profile_names: Vec<&str> = cfg.profiles.keys().map(|name| name.as_str()).collect();
 
-    let day_arg = Arg::with_name("day")
+    let date_arg = Arg::with_name("date")
         .short("d")
-        .long("day")
+        .long("date")
         .takes_value(true)
+        .allow_hyphen_values(true)
         .validator(|v| {
-            if let Ok(n) = v.parse::<u8>() {
-                if n > 5 {
-                    Err("Day must be less than 6".to_string())
-                } else {
-                    Ok(())
-                }
-            } else {
-                Err("Day must be a number".to_string())
-            }
+            date::parse(&v, Local::now().naive_local().date()).map(|_| ())
+        })
+        .help("Select date, like 2021-11-24, 2021-W47, today, tomorrow, next monday or +2w");
+    let theme_arg = Arg::with_name("theme")
+        .long("theme")
+        .takes_value(true)
+        .possible_values(&theme::THEMES)
+        .help("Built-in theme to use instead of the one in the config file");
+    let output_arg = Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .default_value("-")
+        .help("Output file");
+    let resolution_arg = Arg::with_name("resolution")
+        .short("r")
+        .long("resolution")
+        .takes_value(true)
+        .validator(|v| {
+            v.parse::<Dimensions>().map(|_| ()).map_err(|_| "Invalid resolution".to_string())
         })
-        .help("Select what day to print");
+        .default_value("1920x1080")
+        .help("Image resolution");
     let week_arg = Arg::with_name("week")
         .short("w")
         .long("week")
         .takes_value(true)
+        .conflicts_with_all(&["date", "from"])
         .validator(|v| {
-            if let Err(_) = v.parse::<u8>() { Err("Day must be a number".to_string()) }
-            else { Ok(()) }
+            match v.parse::<u8>() {
+                Ok(n) if (1..=53).contains(&n) => Ok(()),
+                _ => Err("Week must be a number from 1 to 53".to_string()),
+            }
+        })
+        .help("Select ISO week, for the whole week");
+    let year_arg = Arg::with_name("year")
+        .short("y")
+        .long("year")
+        .takes_value(true)
+        .requires("week")
+        .validator(|v| {
+            v.parse::<i32>().map(|_| ()).map_err(|_| "Year must be a number".to_string())
         })
-        .help("Select what day to print");
+        .help("ISO week year of --week, defaults to the current one");
+    let whole_week_arg = Arg::with_name("whole-week")
+        .long("whole-week")
+        .takes_value(false)
+        .conflicts_with("from")
+        .help("Show the whole week of --date instead of a single day");
+    let from_arg = Arg::with_name("from")
+        .long("from")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+        .requires("to")
+        .conflicts_with("date")
+        .validator(|v| {
+            date::parse(&v, Local::now().naive_local().date()).map(|_| ())
+        })
+        .help("First date of a range to export, takes the same dates as --date");
+    let to_arg = Arg::with_name("to")
+        .long("to")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+        .requires("from")
+        .validator(|v| {
+            date::parse(&v, Local::now().naive_local().date()).map(|_| ())
+        })
+        .help("Last date of a range to export, takes the same dates as --date");
 
     let matches = App::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!("\n"))
         .about(crate_description!())
+        .subcommand(SubCommand::with_name("init")
+                    .about("Set up the domain, school and class interactively")
+                    .arg(Arg::with_name("name")
+                         .help("Save as a named profile instead of the default selection")))
         .subcommand(SubCommand::with_name("test")
                     .about("Shows test message"))
         .subcommand(SubCommand::with_name("status")
                     .about("Prints current and next lesson. (Default)"))
         .subcommand(SubCommand::with_name("tui")
                     .about("Shows the terminal user interface")
+                    .arg(&theme_arg)
                     .subcommand(SubCommand::with_name("week")
                         .about("Shows entire week")
-                        .arg(Arg::with_name("week")
-                            .short("w")
-                            .long("week")
-                            .takes_value(true)))
+                        .arg(&date_arg)
+                        .arg(&theme_arg))
                     .subcommand(SubCommand::with_name("day")
                         .about("Only show single day")
-                        .arg(&week_arg)
-                        .arg(&day_arg)))
+                        .arg(&date_arg)
+                        .arg(&theme_arg)))
         .subcommand(SubCommand::with_name("lesson_info")
                     .about("Print the lesson info json")
-                    .arg(&day_arg))
+                    .arg(&date_arg)
+                    .arg(&from_arg)
+                    .arg(&to_arg))
         .subcommand(SubCommand::with_name("svg")
                     .about("Generate SVG")
                     .arg(Arg::with_name("output")
@@ -93,17 +182,137 @@ async fn run_commands() -> Result<(), RequestError> {
                          .long("output")
                          .takes_value(true)
                          .default_value("-")
-                         .help("Output file"))
-                    .arg(Arg::with_name("resolution")
-                         .short("r")
-                         .long("resolution")
+                         .help("Output file, with a date range the week is added to the file name"))
+                    .arg(&resolution_arg)
+                    .arg(&date_arg)
+                    .arg(&week_arg)
+                    .arg(&year_arg)
+                    .arg(&whole_week_arg)
+                    .arg(&from_arg)
+                    .arg(&to_arg))
+        .subcommand(SubCommand::with_name("png")
+                    .about("Generate PNG")
+                    .arg(Arg::with_name("output")
+                         .short("o")
+                         .long("output")
+                         .takes_value(true)
+                         .default_value("-")
+                         .help("Output file, with a date range the week is added to the file name"))
+                    .arg(&resolution_arg)
+                    .arg(&date_arg)
+                    .arg(&week_arg)
+                    .arg(&year_arg)
+                    .arg(&whole_week_arg)
+                    .arg(&from_arg)
+                    .arg(&to_arg))
+        .subcommand(SubCommand::with_name("wallpaper")
+                    .about("Generate a wallpaper with today's or the next school day's schedule")
+                    .arg(Arg::with_name("output")
+                         .short("o")
+                         .long("output")
+                         .takes_value(true)
+                         .help("Output file, PNG unless it ends with .svg. Defaults to wallpaper.png in the data directory"))
+                    .arg(resolution_arg.clone().help("Screen resolution"))
+                    .arg(&theme_arg))
+        .subcommand(SubCommand::with_name("markdown")
+                    .about("Render the week as a markdown table")
+                    .arg(&output_arg)
+                    .arg(&date_arg))
+        .subcommand(SubCommand::with_name("html")
+                    .about("Render the week as an HTML page with the lesson colours")
+                    .arg(&output_arg)
+                    .arg(&date_arg))
+        .subcommand(SubCommand::with_name("ics")
+                    .about("Export lessons as an iCalendar (.ics) file")
+                    .arg(&output_arg)
+                    .arg(Arg::with_name("weeks")
+                         .short("n")
+                         .long("weeks")
+                         .takes_value(true)
+                         .default_value("1")
+                         .validator(|v| {
+                             match v.parse::<u8>() {
+                                 Ok(n) if n > 0 => Ok(()),
+                                 _ => Err("Weeks must be a positive number".to_string()),
+                             }
+                         })
+                         .help("Number of consecutive weeks to export"))
+                    .arg(&date_arg)
+                    .arg(from_arg.clone().conflicts_with("weeks"))
+                    .arg(&to_arg))
+        .subcommand(SubCommand::with_name("agenda")
+                    .about("Print a plain text agenda for a day or a week")
+                    .arg(&date_arg)
+                    .arg(Arg::with_name("whole-week")
+                         .long("whole-week")
+                         .takes_value(false)
+                         .help("Show the whole week of --date instead of a single day")))
+        .subcommand(SubCommand::with_name("free")
+                    .about("Find time slots where none of the selected classes have lessons")
+                    .arg(&date_arg)
+                    .arg(Arg::with_name("min-duration")
+                         .short("m")
+                         .long("min-duration")
+                         .takes_value(true)
+                         .default_value("0")
+                         .validator(|v| {
+                             v.parse::<u32>().map(|_| ()).map_err(|_| "Minimum duration must be a number of minutes".to_string())
+                         })
+                         .help("Only show slots at least this many minutes long"))
+                    .arg(Arg::with_name("day-start")
+                         .long("day-start")
+                         .takes_value(true)
+                         .validator(|v| {
+                             NaiveTime::parse_from_str(&v, "%H:%M").map(|_| ()).map_err(|_| "Time must be formatted as HH:MM".to_string())
+                         })
+                         .help("Start of the day, defaults to the first lesson (HH:MM)"))
+                    .arg(Arg::with_name("day-end")
+                         .long("day-end")
+                         .takes_value(true)
+                         .validator(|v| {
+                             NaiveTime::parse_from_str(&v, "%H:%M").map(|_| ()).map_err(|_| "Time must be formatted as HH:MM".to_string())
+                         })
+                         .help("End of the day, defaults to the last lesson (HH:MM)")))
+        .subcommand(SubCommand::with_name("diff")
+                    .about("Show changes since the last stored version of the schedule")
+                    .arg(&date_arg))
+        .subcommand(SubCommand::with_name("watch")
+                    .about("Keep running and notify before lessons and when the schedule changes")
+                    .arg(Arg::with_name("before")
+                         .short("b")
+                         .long("before")
                          .takes_value(true)
+                         .default_value("5")
                          .validator(|v| {
-                             v.parse::<Dimensions>().map(|_| ()).map_err(|_| "Invalid resolution".to_string())
+                             v.parse::<u32>().map(|_| ()).map_err(|_| "Must be a number of minutes".to_string())
                          })
-                         .default_value("1920x1080")
-                         .help("Image resolution"))
-                    .arg(&day_arg))
+                         .help("Minutes before a lesson to notify"))
+                    .arg(Arg::with_name("interval")
+                         .short("i")
+                         .long("interval")
+                         .takes_value(true)
+                         .default_value("15")
+                         .validator(|v| {
+                             match v.parse::<u32>() {
+                                 Ok(n) if n > 0 => Ok(()),
+                                 _ => Err("Must be a positive number of minutes".to_string()),
+                             }
+                         })
+                         .help("Minutes between fetching the schedule"))
+                    .arg(Arg::with_name("exec")
+                         .short("e")
+                         .long("exec")
+                         .takes_value(true)
+                         .help("Shell command to run for notifications, gets the title and message as $1 and $2")))
+        .subcommand(SubCommand::with_name("bar")
+                    .about("Print the current lesson for status bars")
+                    .arg(Arg::with_name("mode")
+                         .short("m")
+                         .long("mode")
+                         .takes_value(true)
+                         .possible_values(&bar::MODES)
+                         .default_value("plain")
+                         .help("Status bar to print for")))
         .subcommand(SubCommand::with_name("list")
                     .about("List schools or classes")
                     .setting(AppSettings::SubcommandRequiredElseHelp)
@@ -115,20 +324,36 @@ async fn run_commands() -> Result<(), RequestError> {
                 .short("c")
                 .long("class")
                 .takes_value(true)
-                .default_value(&cfg.class)
-                .help("Select class"))
+                .multiple(true)
+                .number_of_values(1)
+                .help("Select class, can be given several times to compare classes"))
         .arg(Arg::with_name("school")
                 .short("s")
                 .long("school")
                 .takes_value(true)
-                .default_value(&cfg.school)
                 .help("Select school"))
         .arg(Arg::with_name("domain")
                 .short("d")
                 .long("domain")
                 .takes_value(true)
-                .default_value(&cfg.domain)
                 .help("Select Skola24 domain"))
+        .arg(Arg::with_name("profile")
+                .short("p")
+                .long("profile")
+                .takes_value(true)
+                .possible_values(&profile_names)
+                .help("Select a profile from the config file"))
+        .arg(Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&output::FORMATS)
+                .default_value("text")
+                .global(true)
+                .help("Output format"))
+        .arg(Arg::with_name("offline")
+                .long("offline")
+                .takes_value(false)
+                .help("Only use schedules stored by earlier runs"))
         .arg(Arg::with_name("no-cache")
                 .long("no-cache")
                 .takes_value(false)
@@ -136,115 +361,494 @@ async fn run_commands() -> Result<(), RequestError> {
         .get_matches();
 
     let should_cache = if matches.is_present("no-cache") { false } else { cfg.cache };
+    let offline = matches.is_present("offline");
+    let format: output::Format = matches.value_of("format").unwrap().parse().unwrap();
 
-    let selection = {
-        let domain = matches.value_of("domain").unwrap();
-        let school = get_school_guid(domain, matches.value_of("school").unwrap(), should_cache).await?;
-        let class_guid = get_class_guid(domain, &school, matches.value_of("class").unwrap(), should_cache).await?;
-        (
-            domain.to_string(),
-            school,
-            class_guid
-        )
+    if let Some(i_matches) = matches.subcommand_matches("init") {
+        init::run(i_matches.value_of("name"), should_cache).await?;
+        return Ok(());
+    }
+
+    let mut profile = cfg.profile(matches.value_of("profile"));
+    let mut domain = matches.value_of("domain").unwrap_or(&profile.domain).to_string();
+    let mut school = matches.value_of("school").unwrap_or(&profile.school).to_string();
+    let mut classes: Vec<String> = match matches.values_of("class") {
+        Some(classes) => classes.map(|c| c.to_string()).collect(),
+        None => vec![profile.class.clone()],
     };
+    if domain.is_empty() || school.is_empty() || classes.iter().any(|c| c.is_empty()) {
+        if !offline && io::stdin().is_tty() && init::confirm("No domain, school or class is configured, set them up now?")? {
+            profile = init::run(matches.value_of("profile"), should_cache).await?;
+            domain = profile.domain;
+            school = profile.school;
+            classes = vec![profile.class];
+        } else {
+            println!("You should probably add the domain, school and class to the config file which should be at $XDG_CONFIG_HOME/{0}/{0}.toml, or run the init subcommand", env!("CARGO_PKG_NAME"));
+        }
+    }
+
+    let selections = {
+        let domain = &domain;
+        let school = match store::get_school_guid(domain, &school, should_cache, offline).await {
+            Ok(guid) => guid,
+            Err(e) => {
+                let names = if offline { Vec::new() } else {
+                    get_schools(domain, should_cache).await.map(|s| s.iter().map(|s| s.unit_id.to_string()).collect()).unwrap_or_default()
+                };
+                match suggest("school", &school, &names) {
+                    Some(name) if name != school => {
+                        school = name;
+                        store::get_school_guid(domain, &school, should_cache, offline).await?
+                    },
+                    _ => return Err(e),
+                }
+            },
+        };
+        let mut selections = Vec::new();
+        for class in classes.iter_mut() {
+            let class_guid = match store::get_class_guid(domain, &school, class, should_cache, offline).await {
+                Ok(guid) => guid,
+                Err(e) => {
+                    let names = if offline { Vec::new() } else {
+                        get_classes(domain, &school, should_cache).await.map(|c| c.iter().map(|c| c.group_name.to_string()).collect()).unwrap_or_default()
+                    };
+                    match suggest("class", class, &names) {
+                        Some(name) if name != *class => {
+                            *class = name;
+                            store::get_class_guid(domain, &school, class, should_cache, offline).await?
+                        },
+                        _ => return Err(e),
+                    }
+                },
+            };
+            selections.push((
+                domain.to_string(),
+                school.clone(),
+                class_guid
+            ));
+        }
+        selections
+    };
+    let selection = selections[0].clone();
 
     if matches.subcommand_matches("test").is_some() {
         println!("Test, {}", get_school_guid(&cfg.domain, &cfg.class, should_cache).await?);
     }
     else if matches.subcommand_matches("status").is_some() {
-        status(selection, should_cache).await?;
+        status(&selections, &classes, format, should_cache, offline).await?;
     }
     else if let Some(matches) = matches.subcommand_matches("lesson_info") {
-        println!("{}", serde_json::to_string_pretty(
-            &get_lesson_info(
-                selection,
-                matches.value_of("day").unwrap_or("0").parse()?,
-                Local::now().iso_week().week() as i32,
-                should_cache
-            ).await?
-        )?);
+        if let Some((from, to)) = range_of(matches) {
+            let weeks = get_weeks(&selection, &date::weeks(from, to), should_cache, offline).await?;
+            let lesson_info: Vec<(NaiveDate, &data::LessonInfo)> = weeks.iter()
+                .flat_map(|(week, lessons)| lessons.iter().filter_map(move |l| Some((week.day(l.day_of_week_number)?, l))))
+                .filter(|(date, _)| from <= *date && *date <= to)
+                .collect();
+            match format {
+                output::Format::Text | output::Format::Json => {
+                    let mut values = Vec::new();
+                    for (date, lesson) in lesson_info {
+                        let mut value = serde_json::to_value(lesson)?;
+                        if let Some(object) = value.as_object_mut() {
+                            object.insert("date".to_string(), serde_json::Value::String(date.to_string()));
+                        }
+                        values.push(value);
+                    }
+                    println!("{}", serde_json::to_string_pretty(&values)?);
+                },
+                _ => {
+                    let rows: Vec<Vec<String>> = lesson_info.iter().map(|(date, l)| {
+                        let mut row = vec![date.to_string()];
+                        row.extend(output::lesson_row(&classes[0], l));
+                        row
+                    }).collect();
+                    output::print_table(format, &output::DATED_LESSON_HEADERS, &rows);
+                },
+            }
+            return finish();
+        }
+        let (day, week) = match date_of(matches) {
+            Some(date) => date::selection(date::school_day(date)),
+            None => (0, Week::current()),
+        };
+        let lesson_info = store::get_lesson_info(
+            selection,
+            day,
+            week,
+            should_cache,
+            offline
+        ).await?;
+        match format {
+            output::Format::Text | output::Format::Json => println!("{}", serde_json::to_string_pretty(&lesson_info)?),
+            _ => {
+                let rows: Vec<Vec<String>> = lesson_info.iter().map(|l| output::lesson_row(&classes[0], l)).collect();
+                output::print_table(format, &output::LESSON_HEADERS, &rows);
+            },
+        }
     }
     else if let Some(t_matches) = matches.subcommand_matches("tui") {
-        if let Some(t_matches) = t_matches.subcommand_matches("week") {
-            let week = if let Some(week) = t_matches.value_of("week") {
-                week.parse::<i32>()?
-            } else { Local::now().iso_week().week() as i32 };
-            show_tui(selection, week, None, should_cache).await?;
+        if let Some(w_matches) = t_matches.subcommand_matches("week") {
+            let week = match date_of(w_matches) {
+                Some(date) => Week::of(date),
+                None => Week::current(),
+            };
+            let palette = palette(&cfg.theme, w_matches.value_of("theme").or_else(|| t_matches.value_of("theme")))?;
+            show_tui(&selections, &classes, week, None, &palette, should_cache, offline).await?;
         }
-        else if let Some(t_matches) = t_matches.subcommand_matches("day") {
-            let week = if let Some(week) = t_matches.value_of("week") {
-                week.parse::<i32>()?
-            } else { Local::now().iso_week().week() as i32 };
-            let day = if let Some(day) = t_matches.value_of("day") {
-                day.parse::<i32>().ok()
-            } else { Some(0) };
-            show_tui(selection, week, day, should_cache).await?;
+        else if let Some(d_matches) = t_matches.subcommand_matches("day") {
+            let (day, week) = match date_of(d_matches) {
+                Some(date) => {
+                    let (day, week) = date::selection(date::school_day(date));
+                    (Some(day), week)
+                },
+                None => (Some(0), Week::current()),
+            };
+            let palette = palette(&cfg.theme, d_matches.value_of("theme").or_else(|| t_matches.value_of("theme")))?;
+            show_tui(&selections, &classes, week, day, &palette, should_cache, offline).await?;
         }
         else {
-            show_tui(selection, Local::now().iso_week().week() as i32, Some(0), should_cache).await?;
+            let palette = palette(&cfg.theme, t_matches.value_of("theme"))?;
+            show_tui(&selections, &classes, Week::current(), Some(0), &palette, should_cache, offline).await?;
         }
     }
-    else if let Some(s_matches) = matches.subcommand_matches("svg") {
+    else if let Some(s_matches) = matches.subcommand_matches("svg").or_else(|| matches.subcommand_matches("png")) {
+        let png = matches.subcommand_matches("png").is_some();
+        if let Some((from, to)) = range_of(s_matches) {
+            create_svgs(
+                selection,
+                &date::weeks(from, to),
+                s_matches.value_of("resolution").unwrap(),
+                s_matches.value_of("output").unwrap(),
+                png,
+                should_cache,
+                offline
+            ).await?;
+            return finish();
+        }
+        let selected = match (week_of(s_matches), date_of(s_matches)) {
+            (Some(week), _) => (0, week),
+            (None, Some(date)) if s_matches.is_present("whole-week") => (0, Week::of(date)),
+            (None, Some(date)) => date::selection(date::school_day(date)),
+            (None, None) => (0, Week::current()),
+        };
         create_svg(
             selection,
-            s_matches.value_of("day").unwrap_or("0").parse().unwrap(),
-            s_matches.value_of("resolution").unwrap().parse().unwrap(),
+            selected,
+            s_matches.value_of("resolution").unwrap(),
             s_matches.value_of("output").unwrap(),
+            png,
+            should_cache,
+            offline
+        ).await?;
+    }
+    else if let Some(w_matches) = matches.subcommand_matches("wallpaper") {
+        let output = match w_matches.value_of("output") {
+            Some(output) => std::path::PathBuf::from(output),
+            None => wallpaper_path()?,
+        };
+        let colors = cfg.theme.wallpaper(w_matches.value_of("theme")).map_err(|e| {
+            eprintln!("{}", e);
+            io::Error::new(io::ErrorKind::InvalidInput, e)
+        })?;
+        create_wallpaper(selection, w_matches.value_of("resolution").unwrap(), &output, &colors, should_cache, offline).await?;
+        println!("{}", output.display());
+    }
+    else if let Some(p_matches) = matches.subcommand_matches("markdown").or_else(|| matches.subcommand_matches("html")) {
+        let week = match date_of(p_matches) {
+            Some(date) => Week::of(date),
+            None => Week::current(),
+        };
+        let lesson_info = get_full_week(selection, week, should_cache, offline).await?;
+        let page = if matches.subcommand_matches("html").is_some() {
+            page::html(week, &lesson_info)
+        } else {
+            page::markdown(week, &lesson_info)
+        };
+        write_output(p_matches.value_of("output").unwrap(), page.as_bytes())?;
+    }
+    else if let Some(i_matches) = matches.subcommand_matches("ics") {
+        let range = match range_of(i_matches) {
+            Some(range) => range,
+            None => {
+                let date = date_of(i_matches).unwrap_or_else(|| Local::now().naive_local().date());
+                let weeks: i64 = i_matches.value_of("weeks").unwrap().parse()?;
+                (date::monday(date), date::monday(date) + Duration::weeks(weeks) - Duration::days(1))
+            },
+        };
+        create_ics(
+            selection,
+            range,
+            i_matches.value_of("output").unwrap(),
+            should_cache,
+            offline
+        ).await?;
+    }
+    else if let Some(a_matches) = matches.subcommand_matches("agenda") {
+        let date = date::school_day(date_of(a_matches).unwrap_or_else(|| Local::now().naive_local().date()));
+        show_agenda(&selections, &classes, date, a_matches.is_present("whole-week"), format, should_cache, offline).await?;
+    }
+    else if let Some(f_matches) = matches.subcommand_matches("free") {
+        let week = match date_of(f_matches) {
+            Some(date) => Week::of(date),
+            None => Week::current(),
+        };
+        show_free(
+            &selections,
+            week,
+            (
+                f_matches.value_of("day-start").map(|t| NaiveTime::parse_from_str(t, "%H:%M").unwrap()),
+                f_matches.value_of("day-end").map(|t| NaiveTime::parse_from_str(t, "%H:%M").unwrap()),
+            ),
+            Duration::minutes(f_matches.value_of("min-duration").unwrap().parse()?),
+            format,
+            should_cache,
+            offline
+        ).await?;
+    }
+    else if let Some(d_matches) = matches.subcommand_matches("diff") {
+        let week = match date_of(d_matches) {
+            Some(date) => Week::of(date),
+            None => Week::current(),
+        };
+        show_diff(&selections, &classes, week, format, should_cache).await?;
+    }
+    else if let Some(w_matches) = matches.subcommand_matches("watch") {
+        watch(
+            &selections,
+            &classes,
+            w_matches.value_of("before").unwrap().parse()?,
+            w_matches.value_of("interval").unwrap().parse()?,
+            w_matches.value_of("exec"),
+            format,
             should_cache
         ).await?;
     }
+    else if let Some(b_matches) = matches.subcommand_matches("bar") {
+        let (lesson_info, next_day, _, _) = get_next_lesson_info(selection, should_cache, offline).await?;
+        let state = bar::state(&lesson_info, next_day, Local::now().time());
+        println!("{}", bar::render(b_matches.value_of("mode").unwrap(), &state));
+    }
     else if let Some(l_matches) = matches.subcommand_matches("list") {
         if l_matches.subcommand_matches("schools").is_some() {
-            show_schools(selection, should_cache).await?;
+            show_schools(selection, format, should_cache).await?;
         } else if l_matches.subcommand_matches("classes").is_some() {
-            show_classes(selection, should_cache).await?;
+            show_classes(selection, format, should_cache).await?;
         } else {
             unreachable!()
         }
     }
     else {
-        status(selection, should_cache).await?;
+        status(&selections, &classes, format, should_cache, offline).await?;
     }
 
+    finish()
+}
+
+fn finish() -> Result<(), RequestError> {
+    if let Some(message) = store::age_message() {
+        eprintln!("{}", message);
+    }
     Ok(())
 }
 
-async fn create_svg(selection: (String, String, String), day: i32, res: Dimensions, output: &str, should_cache: bool) -> Result<(), RequestError> {
-    let week = Local::now().iso_week().week() as i32;
-    let schema = get_schema(selection, day, week, Some(res), should_cache).await?.data;
+fn palette(theme: &theme::Theme, base: Option<&str>) -> io::Result<theme::Palette> {
+    theme.palette(base).map_err(|e| {
+        eprintln!("{}", e);
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    })
+}
 
+fn date_of(matches: &ArgMatches) -> Option<NaiveDate> {
+    matches.value_of("date").map(|d| date::parse(d, Local::now().naive_local().date()).unwrap())
+}
+
+fn week_of(matches: &ArgMatches) -> Option<Week> {
+    let week = matches.value_of("week")?.parse().unwrap();
+    let year = match matches.value_of("year") {
+        Some(year) => year.parse().unwrap(),
+        None => Week::current().year,
+    };
+    match Week::new(year, week) {
+        Some(week) => Some(week),
+        None => clap::Error::with_description(&format!("{} has no week {}", year, week), clap::ErrorKind::ValueValidation).exit(),
+    }
+}
+
+fn range_of(matches: &ArgMatches) -> Option<(NaiveDate, NaiveDate)> {
+    let today = Local::now().naive_local().date();
+    let from = date::parse(matches.value_of("from")?, today).unwrap();
+    let to = date::parse(matches.value_of("to")?, today).unwrap();
+    if from > to {
+        clap::Error::with_description("--from must not be after --to", clap::ErrorKind::ValueValidation).exit();
+    }
+    Some((from, to))
+}
+
+// Looks up a school or class name the user typed among the known names,
+// printing suggestions when it can't be resolved
+fn suggest(what: &str, query: &str, candidates: &[String]) -> Option<String> {
+    if candidates.is_empty() {
+        eprintln!("Could not find {} \"{}\"", what, query);
+        return None;
+    }
+
+    match fuzzy::resolve(query, candidates) {
+        fuzzy::Resolution::Found(name) => {
+            if name != query {
+                eprintln!("Using {} \"{}\"", what, name);
+            }
+            Some(name)
+        },
+        fuzzy::Resolution::Ambiguous(names) => {
+            eprintln!("The {} \"{}\" is ambiguous, did you mean one of:", what, query);
+            for name in names {
+                eprintln!("  {}", name);
+            }
+            None
+        },
+        fuzzy::Resolution::NoMatch(names) => {
+            eprintln!("Could not find {} \"{}\", did you mean one of:", what, query);
+            for name in names {
+                eprintln!("  {}", name);
+            }
+            None
+        },
+    }
+}
+
+// Writes the SVG, or the SVG rasterised to PNG when `png` is set
+async fn create_svg(selection: (String, String, String), (day, week): (i32, Week), resolution: &str, output: &str, png: bool, should_cache: bool, offline: bool) -> Result<(), RequestError> {
+    let doc = get_svg(&selection, day, week, resolution, should_cache, offline).await?;
+    write_output(output, &image_data(doc, png)?)?;
+
+    Ok(())
+}
+
+// One SVG per week, the week is added to the output file name
+async fn create_svgs(selection: (String, String, String), weeks: &[Week], resolution: &str, output: &str, png: bool, should_cache: bool, offline: bool) -> Result<(), RequestError> {
+    if output == "-" || output.is_empty() {
+        clap::Error::with_description("An output file is needed to export a range of weeks", clap::ErrorKind::MissingRequiredArgument).exit();
+    }
+
+    let docs = join_weeks(weeks, |week| get_svg(&selection, 0, week, resolution, should_cache, offline)).await?;
+    for (week, doc) in docs {
+        let path = std::path::Path::new(output);
+        let name = match (path.file_stem(), path.extension()) {
+            (Some(stem), Some(extension)) => format!("{}-{}.{}", stem.to_string_lossy(), week, extension.to_string_lossy()),
+            _ => format!("{}-{}", output, week),
+        };
+        write_output(&path.with_file_name(name).to_string_lossy(), &image_data(doc, png)?)?;
+    }
+
+    Ok(())
+}
+
+async fn create_wallpaper(selection: (String, String, String), resolution: &str, output: &std::path::Path, colors: &wallpaper::Colors, should_cache: bool, offline: bool) -> Result<(), RequestError> {
+    let (_, next_day, day, week) = get_next_lesson_info(selection.clone(), should_cache, offline).await?;
+    let label = if next_day { "Next:" } else { "Today:" };
+    let title = week.day(day).map(|date| date.format("%A %-d %B").to_string()).unwrap_or_default();
+
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("Can't make a wallpaper of size {}", resolution));
+    let schedule_resolution = wallpaper::schedule_resolution(resolution).ok_or_else(invalid)?;
+    let schedule = get_svg(&selection, day, week, &schedule_resolution, should_cache, offline).await?;
+    let doc = wallpaper::compose(&schedule, resolution, label, &title, colors).ok_or_else(invalid)?;
+
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let png = output.extension() != Some(std::ffi::OsStr::new("svg"));
+    File::create(output)?.write_all(&image_data(doc, png)?)?;
+
+    Ok(())
+}
+
+fn wallpaper_path() -> io::Result<std::path::PathBuf> {
+    match dirs::data_dir() {
+        Some(dir) => Ok(dir.join(env!("CARGO_PKG_NAME")).join("wallpaper.png")),
+        None => Err(io::Error::new(io::ErrorKind::NotFound, "Could not find the data directory, use --output")),
+    }
+}
+
+fn image_data(doc: String, png: bool) -> io::Result<Vec<u8>> {
+    if png {
+        png::render(&doc)
+    } else {
+        Ok(doc.into_bytes())
+    }
+}
+
+async fn get_svg(selection: &(String, String, String), day: i32, week: Week, resolution: &str, should_cache: bool, offline: bool) -> Result<String, RequestError> {
+    let res: Dimensions = resolution.parse().unwrap();
+
+    if offline {
+        return store::load_svg(selection, day, week, resolution);
+    }
+    let schema = store::get_schema(selection, day, week, res, should_cache).await?;
     let doc = image::generate_svg(&schema, res)?.to_string();
+    store::save_svg(selection, day, week, resolution, &doc);
+    Ok(doc)
+}
+
+async fn create_ics(selection: (String, String, String), (from, to): (NaiveDate, NaiveDate), output: &str, should_cache: bool, offline: bool) -> Result<(), RequestError> {
+    let mut lesson_info = get_weeks(&selection, &date::weeks(from, to), should_cache, offline).await?;
+    for (week, lessons) in lesson_info.iter_mut() {
+        lessons.retain(|l| matches!(week.day(l.day_of_week_number), Some(date) if from <= date && date <= to));
+    }
+
+    let calendar = ics::generate(&selection.2, &lesson_info);
+    write_output(output, calendar.as_bytes())?;
+
+    Ok(())
+}
+
+fn write_output(output: &str, data: &[u8]) -> std::io::Result<()> {
     match output {
         "-"|"" => {
-            std::io::stdout().write_all(doc.as_bytes())?;
+            std::io::stdout().write_all(data)?;
         },
         path => {
-            File::create(path)?.write_all(doc.as_bytes())?;
+            File::create(path)?.write_all(data)?;
         },
     }
-
     Ok(())
 }
 
-async fn status(selection: (String, String, String), should_cache: bool) -> Result<(), RequestError> {
-    let (lesson_info, next_day) = get_next_lesson_info(selection, should_cache).await?;
-    print_lessons(&lesson_info[..], next_day)?;
+async fn status(selections: &[(String, String, String)], classes: &[String], format: output::Format, should_cache: bool, offline: bool) -> Result<(), RequestError> {
+    let mut rows = Vec::new();
+    for (selection, class) in selections.iter().zip(classes) {
+        let (lesson_info, next_day, _, _) = get_next_lesson_info(selection.clone(), should_cache, offline).await?;
+        if format != output::Format::Text {
+            rows.extend(lesson_info.iter().map(|l| output::lesson_row(class, l)));
+            continue;
+        }
+        if selections.len() > 1 {
+            println!("{}:", class);
+        }
+        print_lessons(&lesson_info[..], next_day)?;
+    }
+    if format != output::Format::Text {
+        output::print_table(format, &output::LESSON_HEADERS, &rows);
+    }
     Ok(())
 }
 
-async fn show_tui(selection: (String, String, String), week: i32, day: Option<i32>, should_cache: bool) -> Result<(), RequestError> {
-    let info = if let Some(day) = day {
-        if day == 0 { vec!(get_next_lesson_info(selection, should_cache).await?.0) }
-        else {
-            let mut lesson_info: Vec<Vec<data::LessonInfo>> = Vec::new();
-            let i = get_lesson_info(selection, day, week, should_cache).await?;
-            lesson_info.push(i);
-            lesson_info
-        }
-    }
-    else { get_full_week(selection, week, should_cache).await?.to_vec() };
+async fn show_tui(selections: &[(String, String, String)], classes: &[String], week: Week, day: Option<i32>, palette: &theme::Palette, should_cache: bool, offline: bool) -> Result<(), RequestError> {
+    let (week, day) = match day {
+        Some(0) => {
+            let (_, _, day, week) = get_next_lesson_info(selections[0].clone(), should_cache, offline).await?;
+            (week, Some(day))
+        },
+        day => (week, day),
+    };
+    let info = get_columns(selections, week, day, should_cache, offline).await?;
 
-    match stui::run(&info[..]) {
+    let fetch = |week: Week, day: Option<i32>| {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(get_columns(selections, week, day, should_cache, offline))
+        }).map_err(|_| store::error_message().unwrap_or_else(|| "Failed to fetch lessons".to_string()))
+    };
+
+    match stui::run(&info[..], classes, week, day, palette, fetch) {
         Ok(_) => Ok(()),
         Err(e) => {
             println!("Error while running stui: {}", e);
@@ -253,69 +857,335 @@ async fn show_tui(selection: (String, String, String), week: i32, day: Option<i3
     }
 }
 
-async fn show_classes(selection: (String, String, String), should_cache: bool) -> Result<(), RequestError> {
+async fn show_agenda(selections: &[(String, String, String)], classes: &[String], date: NaiveDate, whole_week: bool, format: output::Format, should_cache: bool, offline: bool) -> Result<(), RequestError> {
+    let (day, week) = date::selection(date);
+    let mut rows = Vec::new();
+    for (i, (selection, class)) in selections.iter().zip(classes).enumerate() {
+        let lesson_info = get_full_week(selection.clone(), week, should_cache, offline).await?;
+        let days: Vec<(NaiveDate, &[data::LessonInfo])> = (1..=5)
+            .filter(|d| whole_week || *d == day)
+            .filter_map(|d| Some((week.day(d)?, &lesson_info[d as usize - 1][..])))
+            .collect();
+
+        if format != output::Format::Text {
+            for (date, lessons) in &days {
+                let mut lessons: Vec<&data::LessonInfo> = lessons.iter().filter(|l| lesson::times_or_warn(l).is_some()).collect();
+                lessons.sort_by_key(|l| lesson::start(l).ok());
+                rows.extend(lessons.iter().map(|l| {
+                    let mut row = vec![date.to_string()];
+                    row.extend(output::lesson_row(class, l));
+                    row
+                }));
+            }
+            continue;
+        }
+
+        if selections.len() > 1 {
+            if i > 0 {
+                println!();
+            }
+            println!("{}:", class);
+        }
+        print!("{}", agenda::render(&days));
+    }
+    if format != output::Format::Text {
+        output::print_table(format, &output::DATED_LESSON_HEADERS, &rows);
+    }
+    Ok(())
+}
+
+async fn show_free(selections: &[(String, String, String)], week: Week, (day_start, day_end): (Option<NaiveTime>, Option<NaiveTime>), min_duration: Duration, format: output::Format, should_cache: bool, offline: bool) -> Result<(), RequestError> {
+    let mut weeks = Vec::new();
+    for selection in selections {
+        weeks.push(get_full_week(selection.clone(), week, should_cache, offline).await?);
+    }
+
+    let mut rows = Vec::new();
+    for (i, name) in lesson::DAY_NAMES.iter().enumerate() {
+        let lesson_info: Vec<&[data::LessonInfo]> = weeks.iter().map(|w| &w[i][..]).collect();
+        let slots = free::free_slots(&lesson_info, day_start, day_end, min_duration);
+
+        if format != output::Format::Text {
+            for (from, to) in slots {
+                rows.push(vec![
+                    (i + 1).to_string(),
+                    from.format("%H:%M:%S").to_string(),
+                    to.format("%H:%M:%S").to_string(),
+                    to.signed_duration_since(from).num_minutes().to_string(),
+                ]);
+            }
+            continue;
+        }
+
+        println!("{}", name);
+        if slots.is_empty() && lesson_info.iter().all(|l| l.is_empty()) {
+            println!("  No lessons");
+        } else if slots.is_empty() {
+            println!("  No free slots");
+        }
+        for (from, to) in slots {
+            println!("  {} - {} ({} min)", from.format("%H:%M"), to.format("%H:%M"), to.signed_duration_since(from).num_minutes());
+        }
+    }
+    if format != output::Format::Text {
+        output::print_table(format, &["day", "start", "end", "minutes"], &rows);
+    }
+    Ok(())
+}
+
+async fn show_diff(selections: &[(String, String, String)], classes: &[String], week: Week, format: output::Format, should_cache: bool) -> Result<(), RequestError> {
+    let mut rows = Vec::new();
+    for (selection, class) in selections.iter().zip(classes) {
+        let snapshot = store::load_snapshot(selection, week, "diff");
+        let lesson_info = store::get_lesson_info(selection.clone(), 0, week, should_cache, false).await?;
+        store::save_snapshot(selection, week, "diff", &lesson_info);
+
+        if format != output::Format::Text {
+            if let Some((_, old)) = snapshot {
+                for change in diff::diff(&old, &lesson_info) {
+                    let (old, new) = change.lessons();
+                    let mut row = vec![class.clone(), change.kind().to_string()];
+                    for lesson in [old, new].iter() {
+                        match lesson {
+                            Some(lesson) => row.extend(output::lesson_row(class, lesson).into_iter().skip(1)),
+                            None => row.extend(vec![String::new(); output::LESSON_HEADERS.len() - 1]),
+                        }
+                    }
+                    rows.push(row);
+                }
+            }
+            continue;
+        }
+
+        if selections.len() > 1 {
+            println!("{}:", class);
+        }
+
+        match snapshot {
+            Some((fetched, old)) => {
+                let changes = diff::diff(&old, &lesson_info);
+                if changes.is_empty() {
+                    println!("No changes in week {} since {}", week, fetched.format("%Y-%m-%d %H:%M"));
+                } else {
+                    println!("Changes in week {} since {}", week, fetched.format("%Y-%m-%d %H:%M"));
+                }
+                for change in changes {
+                    println!("  {}", change);
+                }
+            },
+            None => println!("No earlier version of week {} stored, the current one will be compared next time", week),
+        }
+    }
+    if format != output::Format::Text {
+        let headers = [
+            "class", "change",
+            "old_day", "old_start", "old_end", "old_subject", "old_teacher", "old_room",
+            "new_day", "new_start", "new_end", "new_subject", "new_teacher", "new_room",
+        ];
+        output::print_table(format, &headers, &rows);
+    }
+    Ok(())
+}
+
+async fn watch(selections: &[(String, String, String)], classes: &[String], before: i64, interval: u64, exec: Option<&str>, format: output::Format, should_cache: bool) -> Result<(), RequestError> {
+    let mut notified: HashSet<String> = HashSet::new();
+    let mut upcoming: Vec<(String, data::LessonInfo)> = Vec::new();
+    let mut next_poll = std::time::Instant::now();
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(30));
+
+    loop {
+        ticker.tick().await;
+
+        if std::time::Instant::now() >= next_poll {
+            next_poll = std::time::Instant::now() + std::time::Duration::from_secs(interval * 60);
+            upcoming.clear();
+
+            for (selection, class) in selections.iter().zip(classes) {
+                let (lesson_info, next_day, _, week) = match get_next_lesson_info(selection.clone(), should_cache, false).await {
+                    Ok(info) => info,
+                    Err(_) => {
+                        if let Some(message) = store::error_message() {
+                            eprintln!("{}", message);
+                        }
+                        eprintln!("Failed to fetch lessons for {}, retrying in {} minutes", class, interval);
+                        continue;
+                    },
+                };
+                if !next_day {
+                    upcoming.extend(lesson_info.into_iter().map(|l| (class.clone(), l)));
+                }
+
+                let snapshot = store::load_snapshot(selection, week, "watch");
+                if let Ok(week_info) = store::get_lesson_info(selection.clone(), 0, week, should_cache, false).await {
+                    store::save_snapshot(selection, week, "watch", &week_info);
+                    if let Some((_, old)) = snapshot {
+                        for change in diff::diff(&old, &week_info) {
+                            notify(exec, format, &format!("Schedule changed for {}", class), &change.to_string()).await;
+                        }
+                    }
+                }
+            }
+        }
+
+        let now = Local::now();
+        for (class, lesson) in &upcoming {
+            let time_start = match lesson::start(lesson) {
+                Ok(time) => time,
+                Err(_) => continue,
+            };
+            let until = time_start.signed_duration_since(now.time());
+            if until <= Duration::zero() || until > Duration::minutes(before) {
+                continue;
+            }
+
+            let key = format!("{} {} {} {}", class, now.date().naive_local(), lesson.time_start, lesson::text(lesson, 0));
+            if notified.insert(key) {
+                let title = format!("{} in {} min", lesson::text(lesson, 0), until.num_minutes() + 1);
+                let message = format!("{} at {}", lesson::details(lesson, ", "), time_start.format("%H:%M"));
+                notify(exec, format, &title, &message).await;
+            }
+        }
+    }
+}
+
+async fn notify(exec: Option<&str>, format: output::Format, title: &str, message: &str) {
+    match exec {
+        Some(command) => {
+            let status = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .arg(env!("CARGO_PKG_NAME"))
+                .arg(title)
+                .arg(message)
+                .status()
+                .await;
+            if status.is_err() {
+                eprintln!("Failed to run notification command");
+            }
+        },
+        None if format == output::Format::Text => println!("[{}] {}: {}", Local::now().format("%H:%M"), title, message),
+        None => output::print_record(format, &["time", "title", "message"], &[Local::now().to_rfc3339(), title.to_string(), message.to_string()]),
+    }
+}
+
+async fn show_classes(selection: (String, String, String), format: output::Format, should_cache: bool) -> Result<(), RequestError> {
     let classes = get_classes(&selection.0, &selection.1, should_cache).await?;
+    if format != output::Format::Text {
+        let rows: Vec<Vec<String>> = classes.iter().map(|c| vec![c.group_name.to_string()]).collect();
+        output::print_table(format, &["class"], &rows);
+        return Ok(());
+    }
     for class in &classes {
         println!("{}", class.group_name);
     }
     Ok(())
 }
 
-async fn show_schools(selection: (String, String, String), should_cache: bool) -> Result<(), RequestError> {
+async fn show_schools(selection: (String, String, String), format: output::Format, should_cache: bool) -> Result<(), RequestError> {
     let schools = get_schools(&selection.0, should_cache).await?;
+    if format != output::Format::Text {
+        let rows: Vec<Vec<String>> = schools.iter().map(|s| vec![s.unit_id.to_string()]).collect();
+        output::print_table(format, &["school"], &rows);
+        return Ok(());
+    }
     for school in &schools {
         println!("{}", school.unit_id);
     }
     Ok(())
 }
 
-async fn get_next_lesson_info(selection: (String, String, String), should_cache: bool) -> Result<(Vec<data::LessonInfo>, bool), RequestError> {
-    let now = Local::now();
-    let mut day: i32 = now.weekday().number_from_monday().try_into().unwrap();
-    let mut week: i32 = now.iso_week().week() as i32;
-
-    let mut next_day = false;
-
-    if day > 5 {
-        next_day = true;
-        day = 1;
-        week += 1;
-    }
-
+async fn get_next_lesson_info(selection: (String, String, String), should_cache: bool, offline: bool) -> Result<(Vec<data::LessonInfo>, bool, i32, Week), RequestError> {
+    let now = Local::now().naive_local();
+    let mut date = date::school_day(now.date());
+    let mut next_day = date != now.date();
 
-    let lesson_info = if next_day {
-        get_lesson_info(selection, day, week, should_cache).await?
-    }
-    else {
-        let lesson_info = get_lesson_info(selection.clone(), day, week, should_cache).await?;
+    if !next_day {
+        let (day, week) = date::selection(date);
+        let lesson_info = store::get_lesson_info(selection.clone(), day, week, should_cache, offline).await?;
         let mut last_lesson = NaiveTime::from_hms(0,0,0);
-        for lesson in lesson_info {
-            let time = NaiveTime::parse_from_str(&lesson.time_end.to_string(), "%H:%M:%S").expect("Failed to parse time!");
+        for time in lesson_info.iter().filter_map(|lesson| lesson::end(lesson).ok()) {
             if time > last_lesson {
                 last_lesson = time;
             }
         }
 
-        if Local::now().time() > last_lesson {
-            next_day = true;
-            day += 1;
-            if day > 5 {
-                day = 1;
-                week += 1;
-            }
+        if now.time() <= last_lesson {
+            return Ok((lesson_info, next_day, day, week));
         }
-        get_lesson_info(selection, day, week, should_cache).await?
-    };
+        next_day = true;
+        date = date::next_school_day(date);
+    }
+
+    let (day, week) = date::selection(date);
+    let lesson_info = store::get_lesson_info(selection, day, week, should_cache, offline).await?;
 
-    Ok((lesson_info, next_day))
+    Ok((lesson_info, next_day, day, week))
 }
 
-async fn get_full_week(selection: (String, String, String), week: i32, should_cache: bool) -> Result<[Vec<data::LessonInfo>; 5], RequestError> {
+// Fetches all weeks at once, in the same order as they are given
+async fn get_weeks(selection: &(String, String, String), weeks: &[Week], should_cache: bool, offline: bool) -> Result<Vec<(Week, Vec<data::LessonInfo>)>, RequestError> {
+    join_weeks(weeks, |week| store::get_lesson_info(selection.clone(), 0, week, should_cache, offline)).await
+}
+
+// Fetches all weeks at once. Weeks that fail are left out with a warning,
+// it is only an error when none of them could be fetched
+async fn join_weeks<T, F, Fut>(weeks: &[Week], fetch: F) -> Result<Vec<(Week, T)>, RequestError>
+where
+    F: Fn(Week) -> Fut,
+    Fut: std::future::Future<Output = Result<T, RequestError>>,
+{
+    let results = futures::future::join_all(weeks.iter().map(|week| {
+        let fetched = fetch(*week);
+        // The store message is taken right away, before another week can fail
+        async move { fetched.await.map_err(|e| (e, store::error_message())) }
+    })).await;
+
+    let mut fetched = Vec::new();
+    let mut error = None;
+    for (week, result) in weeks.iter().zip(results) {
+        match result {
+            Ok(data) => fetched.push((*week, data)),
+            Err((e, message)) => {
+                eprintln!("Skipping week {}: {}", week, message.unwrap_or_else(|| "Failed to fetch it".to_string()));
+                error = Some(e);
+            },
+        }
+    }
+    match error {
+        Some(e) if fetched.is_empty() => Err(e),
+        _ => Ok(fetched),
+    }
+}
+
+// One column per class for a single day, or per day and class for a whole week
+async fn get_columns(selections: &[(String, String, String)], week: Week, day: Option<i32>, should_cache: bool, offline: bool) -> Result<Vec<Vec<data::LessonInfo>>, RequestError> {
+    let mut per_class = Vec::new();
+    for selection in selections {
+        per_class.push(match day {
+            Some(day) => vec!(store::get_lesson_info(selection.clone(), day, week, should_cache, offline).await?),
+            None => get_full_week(selection.clone(), week, should_cache, offline).await?.to_vec(),
+        });
+    }
+
+    let mut columns = Vec::new();
+    for i in 0..per_class.first().map_or(0, |c| c.len()) {
+        for class in &per_class {
+            columns.push(class[i].clone());
+        }
+    }
+    Ok(columns)
+}
+
+async fn get_full_week(selection: (String, String, String), week: Week, should_cache: bool, offline: bool) -> Result<[Vec<data::LessonInfo>; 5], RequestError> {
     let mut lesson_info: [Vec<data::LessonInfo>; 5] = Default::default();
 
-    let next_lesson_info = &get_lesson_info(selection, 0, week, should_cache).await?;
-    for i in 0..next_lesson_info.len() {
-        lesson_info[next_lesson_info[i].day_of_week_number as usize - 1].push(next_lesson_info[i].clone());
+    let next_lesson_info = &store::get_lesson_info(selection, 0, week, should_cache, offline).await?;
+    for lesson in next_lesson_info {
+        // Lessons outside monday to friday are malformed and left out, this
+        // also runs inside the TUI so nothing is printed
+        if let Some(day) = (lesson.day_of_week_number as usize).checked_sub(1).and_then(|i| lesson_info.get_mut(i)) {
+            day.push(lesson.clone());
+        }
     }
 
     Ok(lesson_info)
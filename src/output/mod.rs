@@ -0,0 +1,97 @@
+use libschedule24::data;
+
+use std::str::FromStr;
+
+use crate::lesson;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+    Tsv,
+}
+
+pub const FORMATS: [&str; 4] = ["text", "json", "csv", "tsv"];
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!("Unknown format {}", s)),
+        }
+    }
+}
+
+pub const LESSON_HEADERS: [&str; 7] = ["class", "day", "start", "end", "subject", "teacher", "room"];
+pub const DATED_LESSON_HEADERS: [&str; 8] = ["date", "class", "day", "start", "end", "subject", "teacher", "room"];
+
+pub fn lesson_row(class: &str, lesson: &data::LessonInfo) -> Vec<String> {
+    vec![
+        class.to_string(),
+        lesson.day_of_week_number.to_string(),
+        lesson.time_start.to_string(),
+        lesson.time_end.to_string(),
+        lesson::text(lesson, 0),
+        lesson::text(lesson, 1),
+        lesson::text(lesson, 2),
+    ]
+}
+
+/// Prints rows as a JSON array of objects keyed by the headers, or as CSV/TSV
+/// with a header line. Text output is left to the individual commands.
+pub fn print_table(format: Format, headers: &[&str], rows: &[Vec<String>]) {
+    match format {
+        Format::Json => {
+            let objects: Vec<serde_json::Value> = rows.iter().map(|row| object(headers, row)).collect();
+            println!("{}", serde_json::Value::Array(objects));
+        },
+        Format::Csv | Format::Tsv | Format::Text => {
+            println!("{}", line(format, headers.iter().map(|h| h.to_string())));
+            for row in rows {
+                println!("{}", line(format, row.iter().cloned()));
+            }
+        },
+    }
+}
+
+/// Prints a single row for streaming output, as one JSON object per line or
+/// as a CSV/TSV line without headers.
+pub fn print_record(format: Format, headers: &[&str], row: &[String]) {
+    match format {
+        Format::Json => println!("{}", object(headers, row)),
+        _ => println!("{}", line(format, row.iter().cloned())),
+    }
+}
+
+fn object(headers: &[&str], row: &[String]) -> serde_json::Value {
+    let mut object = serde_json::Map::new();
+    for (header, value) in headers.iter().zip(row) {
+        object.insert(header.to_string(), serde_json::Value::String(value.clone()));
+    }
+    serde_json::Value::Object(object)
+}
+
+fn line<I: Iterator<Item = String>>(format: Format, fields: I) -> String {
+    match format {
+        Format::Tsv => fields
+            .map(|f| f.replace(['\t', '\n', '\r'], " "))
+            .collect::<Vec<String>>()
+            .join("\t"),
+        _ => fields
+            .map(|f| {
+                if f.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", f.replace('"', "\"\""))
+                } else {
+                    f
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(","),
+    }
+}
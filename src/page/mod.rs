@@ -0,0 +1,131 @@
+use libschedule24::data;
+
+use chrono::NaiveTime;
+
+use crate::{date::Week, lesson::{self, DAY_NAMES}};
+
+/// Renders a week as a markdown table with one column per day and one row
+/// per lesson time.
+pub fn markdown(week: Week, lesson_info: &[Vec<data::LessonInfo>; 5]) -> String {
+    let mut out = format!("## {}\n\n", title(week));
+
+    out.push_str("| Time |");
+    for (i, name) in DAY_NAMES.iter().enumerate() {
+        out.push_str(&format!(" {} {} |", name, day(week, i)));
+    }
+    out.push_str("\n|---|");
+    out.push_str(&"---|".repeat(DAY_NAMES.len()));
+    out.push('\n');
+
+    for (start, end, cells) in rows(lesson_info) {
+        out.push_str(&format!("| {}-{} |", start.format("%H:%M"), end.format("%H:%M")));
+        for lessons in cells {
+            let text = lessons.iter()
+                .map(|lesson| describe(lesson).iter().filter(|t| !t.is_empty()).map(|t| markdown_escape(t)).collect::<Vec<String>>().join(", "))
+                .collect::<Vec<String>>()
+                .join("<br>");
+            out.push_str(&format!(" {} |", text));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Renders a week as a standalone HTML page, every lesson keeps the
+/// background colour it has in Skola24.
+pub fn html(week: Week, lesson_info: &[Vec<data::LessonInfo>; 5]) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", html_escape(&title(week))));
+    out.push_str("<style>\n");
+    out.push_str("table { border-collapse: collapse; font-family: sans-serif; }\n");
+    out.push_str("th, td { border: 1px solid #888; padding: 4px; vertical-align: top; }\n");
+    out.push_str(".lesson { padding: 4px; margin: 2px 0; color: #000; }\n");
+    out.push_str(".lesson span { display: block; }\n");
+    out.push_str("</style>\n</head>\n<body>\n");
+    out.push_str(&format!("<h2>{}</h2>\n<table>\n<tr><th>Time</th>", html_escape(&title(week))));
+    for (i, name) in DAY_NAMES.iter().enumerate() {
+        out.push_str(&format!("<th>{} {}</th>", name, day(week, i)));
+    }
+    out.push_str("</tr>\n");
+
+    for (start, end, cells) in rows(lesson_info) {
+        out.push_str(&format!("<tr><th>{}-{}</th>", start.format("%H:%M"), end.format("%H:%M")));
+        for lessons in cells {
+            out.push_str("<td>");
+            for lesson in lessons {
+                match lesson::parse_color(&lesson.block.b_color.to_string()) {
+                    Ok(color) => out.push_str(&format!("<div class=\"lesson\" style=\"background-color: {}\">", lesson::hex(color))),
+                    Err(_) => out.push_str("<div class=\"lesson\">"),
+                }
+                for text in describe(lesson).iter().filter(|t| !t.is_empty()) {
+                    out.push_str(&format!("<span>{}</span>", html_escape(text)));
+                }
+                out.push_str("</div>");
+            }
+            out.push_str("</td>");
+        }
+        out.push_str("</tr>\n");
+    }
+
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+fn title(week: Week) -> String {
+    format!("Week {}, {}", week.week, week.year)
+}
+
+fn day(week: Week, i: usize) -> String {
+    week.day(i as i32 + 1).map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default()
+}
+
+// Every distinct lesson time of the week becomes a row, with the lessons of
+// each day that have exactly that time
+type Row<'a> = (NaiveTime, NaiveTime, Vec<Vec<&'a data::LessonInfo>>);
+
+fn rows(lesson_info: &[Vec<data::LessonInfo>; 5]) -> Vec<Row<'_>> {
+    let mut rows: Vec<Row<'_>> = Vec::new();
+    for (i, lessons) in lesson_info.iter().enumerate() {
+        for lesson in lessons {
+            let (start, end) = match lesson::times_or_warn(lesson) {
+                Some(times) => times,
+                None => continue,
+            };
+            match rows.iter_mut().find(|row| row.0 == start && row.1 == end) {
+                Some(row) => row.2[i].push(lesson),
+                None => {
+                    let mut cells = vec![Vec::new(); lesson_info.len()];
+                    cells[i].push(lesson);
+                    rows.push((start, end, cells));
+                },
+            }
+        }
+    }
+    rows.sort_by_key(|row| (row.0, row.1));
+    rows
+}
+
+fn describe(lesson: &data::LessonInfo) -> Vec<String> {
+    lesson.texts.iter().take(3).map(|t| t.to_string()).collect()
+}
+
+fn markdown_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
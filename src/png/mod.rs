@@ -0,0 +1,43 @@
+use std::io;
+
+/// Rasterises an SVG document to PNG. The document is rendered at the size
+/// it declares, which `image::generate_svg` sets from the requested
+/// resolution.
+pub fn render(svg: &str) -> io::Result<Vec<u8>> {
+    let mut options = usvg::Options::default();
+    options.fontdb.load_system_fonts();
+
+    // The generic font families default to Arial and Times New Roman, use
+    // other installed fonts for them when those are missing
+    let faces = options.fontdb.faces();
+    let serif = faces.first().map(|face| face.family.clone());
+    let sans_serif = faces.iter().find(|face| face.family.contains("Sans")).map(|face| face.family.clone()).or_else(|| serif.clone());
+    let missing = |fontdb: &usvg::fontdb::Database, family| fontdb.query(&usvg::fontdb::Query {
+        families: &[family],
+        ..Default::default()
+    }).is_none();
+    if let Some(family) = sans_serif {
+        if missing(&options.fontdb, usvg::fontdb::Family::SansSerif) {
+            options.fontdb.set_sans_serif_family(family);
+        }
+    }
+    if let Some(family) = serif {
+        if missing(&options.fontdb, usvg::fontdb::Family::Serif) {
+            options.fontdb.set_serif_family(family.clone());
+        }
+        if missing(&options.fontdb, usvg::fontdb::Family::Name(&options.font_family)) {
+            options.font_family = family;
+        }
+    }
+
+    let tree = usvg::Tree::from_str(svg, &options.to_ref())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    let size = tree.svg_node().size.to_screen_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "The SVG has no size"))?;
+    resvg::render(&tree, usvg::FitTo::Original, tiny_skia::Transform::default(), pixmap.as_mut())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Failed to render the SVG"))?;
+
+    pixmap.encode_png().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
@@ -0,0 +1,258 @@
+use libschedule24::{data, Dimensions, RequestError};
+
+use std::{
+    collections::HashMap,
+    fs,
+    io,
+    path::PathBuf,
+    sync::{Mutex, atomic::{AtomicI64, Ordering}},
+};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::date::Week;
+
+// Timestamp of the oldest stored data that was used instead of a fresh fetch
+static OLDEST_USED: AtomicI64 = AtomicI64::new(i64::MAX);
+
+// Why the store failed the last time, errors from libschedule24 leave it empty.
+// Nothing is printed here since the TUI may be drawing on the terminal
+static ERROR_MESSAGE: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Deserialize, Serialize)]
+struct Stored<T> {
+    fetched: i64,
+    data: T,
+}
+
+fn root() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join("store"))
+}
+
+fn selection_dir(selection: &(String, String, String)) -> Option<PathBuf> {
+    root().map(|root| root.join(&selection.0).join(&selection.1).join(&selection.2))
+}
+
+fn load<T: DeserializeOwned>(path: PathBuf) -> Option<Stored<T>> {
+    let file = fs::File::open(path).ok()?;
+    serde_json::from_reader(file).ok()
+}
+
+fn save<T: Serialize>(path: PathBuf, data: T) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let stored = Stored { fetched: Local::now().timestamp(), data };
+    fs::write(path, serde_json::to_vec(&stored)?)
+}
+
+fn used<T>(stored: Stored<T>) -> T {
+    OLDEST_USED.fetch_min(stored.fetched, Ordering::Relaxed);
+    stored.data
+}
+
+fn unavailable(kind: io::ErrorKind, message: String) -> RequestError {
+    set_error_message(Some(message.clone()));
+    io::Error::new(kind, message).into()
+}
+
+fn failed(e: RequestError) -> RequestError {
+    set_error_message(None);
+    e
+}
+
+fn set_error_message(message: Option<String>) {
+    if let Ok(mut error_message) = ERROR_MESSAGE.lock() {
+        *error_message = message;
+    }
+}
+
+/// Describes why the last failed call to the store failed, unless it was
+/// an error from libschedule24.
+pub fn error_message() -> Option<String> {
+    ERROR_MESSAGE.lock().ok()?.take()
+}
+
+fn not_stored(what: String) -> RequestError {
+    unavailable(io::ErrorKind::NotFound, format!("No offline data for {}, run once without --offline to store it", what))
+}
+
+/// Describes how old the oldest stored data used by this run is, if any was used.
+pub fn age_message() -> Option<String> {
+    let fetched = OLDEST_USED.load(Ordering::Relaxed);
+    if fetched == i64::MAX {
+        return None;
+    }
+
+    let fetched = Local.timestamp(fetched, 0);
+    let age = Local::now().signed_duration_since(fetched);
+    let age = if age.num_days() > 0 {
+        format!("{} days", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{} hours", age.num_hours())
+    } else {
+        format!("{} minutes", age.num_minutes())
+    };
+    Some(format!("Using offline data fetched {} ago ({})", age, fetched.format("%Y-%m-%d %H:%M")))
+}
+
+/// libschedule24 is only given a week number and takes the year from the
+/// calendar, so only weeks with the same ISO week year as today's calendar
+/// year can be fetched. Around new year that leaves out weeks that are
+/// right next to today.
+pub fn fetchable(week: Week) -> bool {
+    fetchable_on(week, Local::now().naive_local().date())
+}
+
+fn fetchable_on(week: Week, today: NaiveDate) -> bool {
+    week.year == today.year()
+}
+
+fn not_fetchable(week: Week) -> RequestError {
+    unavailable(io::ErrorKind::InvalidInput, format!("Can't fetch week {}, Skola24 can only be asked for weeks in {}", week, Local::now().year()))
+}
+
+/// Same as `libschedule24::get_lesson_info`, but every fetched result is
+/// stored locally. When `offline` is set, the fetch fails or the week can't
+/// be fetched, the stored result is used instead.
+pub async fn get_lesson_info(selection: (String, String, String), day: i32, week: Week, should_cache: bool, offline: bool) -> Result<Vec<data::LessonInfo>, RequestError> {
+    let fetch = !offline && fetchable(week);
+
+    if fetch {
+        match libschedule24::get_lesson_info(selection.clone(), day, week.week, should_cache).await {
+            Ok(lesson_info) => {
+                if let Some(path) = lesson_info_path(&selection, day, week) {
+                    save(path, &lesson_info).ok();
+                }
+                return Ok(lesson_info);
+            },
+            Err(e) => return load_lesson_info(&selection, day, week).map(used).ok_or_else(|| failed(e)),
+        }
+    }
+
+    if let Some(stored) = load_lesson_info(&selection, day, week) {
+        return Ok(used(stored));
+    }
+    if !offline {
+        return Err(not_fetchable(week));
+    }
+    Err(not_stored(format!("week {}", week)))
+}
+
+fn lesson_info_path(selection: &(String, String, String), day: i32, week: Week) -> Option<PathBuf> {
+    selection_dir(selection).map(|dir| dir.join(format!("{}-{}.json", week, day)))
+}
+
+// A stored whole week also covers every single day of it
+fn load_lesson_info(selection: &(String, String, String), day: i32, week: Week) -> Option<Stored<Vec<data::LessonInfo>>> {
+    if let Some(stored) = lesson_info_path(selection, day, week).and_then(load) {
+        return Some(stored);
+    }
+    if day == 0 {
+        return None;
+    }
+    let mut stored: Stored<Vec<data::LessonInfo>> = lesson_info_path(selection, 0, week).and_then(load)?;
+    stored.data.retain(|l| l.day_of_week_number as usize == day as usize);
+    Some(stored)
+}
+
+fn snapshot_path(selection: &(String, String, String), week: Week, owner: &str) -> Option<PathBuf> {
+    selection_dir(selection).map(|dir| dir.join(format!("{}-{}.snapshot.json", week, owner)))
+}
+
+/// The lessons of a week as they were the last time the `owner` command
+/// looked at them and when that was. Kept apart from the stored results of
+/// `get_lesson_info` since every other fetch overwrites those, and for every
+/// command so that `watch` running in the background doesn't use up the
+/// changes `diff` would show.
+pub fn load_snapshot(selection: &(String, String, String), week: Week, owner: &str) -> Option<(DateTime<Local>, Vec<data::LessonInfo>)> {
+    let stored: Stored<Vec<data::LessonInfo>> = snapshot_path(selection, week, owner).and_then(load)?;
+    Some((Local.timestamp(stored.fetched, 0), stored.data))
+}
+
+pub fn save_snapshot(selection: &(String, String, String), week: Week, owner: &str, lesson_info: &[data::LessonInfo]) {
+    if let Some(path) = snapshot_path(selection, week, owner) {
+        save(path, lesson_info).ok();
+    }
+}
+
+/// Stores a generated SVG document, see `load_svg`.
+pub fn save_svg(selection: &(String, String, String), day: i32, week: Week, resolution: &str, doc: &str) {
+    if let Some(dir) = selection_dir(selection) {
+        save(dir.join(format!("{}-{}-{}.svg.json", week, day, resolution)), doc).ok();
+    }
+}
+
+/// Fetches the SVG schema of a week, see `fetchable`.
+pub async fn get_schema(selection: &(String, String, String), day: i32, week: Week, resolution: Dimensions, should_cache: bool) -> Result<data::Schema, RequestError> {
+    if !fetchable(week) {
+        return Err(not_fetchable(week));
+    }
+    Ok(libschedule24::get_schema(selection.clone(), day, week.week, Some(resolution), should_cache).await.map_err(failed)?.data)
+}
+
+pub fn load_svg(selection: &(String, String, String), day: i32, week: Week, resolution: &str) -> Result<String, RequestError> {
+    selection_dir(selection)
+        .and_then(|dir| load(dir.join(format!("{}-{}-{}.svg.json", week, day, resolution))))
+        .map(used)
+        .ok_or_else(|| not_stored(format!("the {} SVG of week {}", resolution, week)))
+}
+
+fn guids_path() -> Option<PathBuf> {
+    root().map(|root| root.join("guids.json"))
+}
+
+/// School and class GUIDs are remembered by name so that a selection can be
+/// resolved without network access.
+pub async fn get_school_guid(domain: &str, school: &str, should_cache: bool, offline: bool) -> Result<String, RequestError> {
+    resolve_guid(format!("{}/{}", domain, school), offline, libschedule24::get_school_guid(domain, school, should_cache)).await
+}
+
+pub async fn get_class_guid(domain: &str, school_guid: &str, class: &str, should_cache: bool, offline: bool) -> Result<String, RequestError> {
+    resolve_guid(format!("{}/{}/{}", domain, school_guid, class), offline, libschedule24::get_class_guid(domain, school_guid, class, should_cache)).await
+}
+
+async fn resolve_guid<F>(key: String, offline: bool, fetch: F) -> Result<String, RequestError>
+where
+    F: std::future::Future<Output = Result<String, RequestError>>,
+{
+    let mut guids: HashMap<String, String> = guids_path().and_then(load).map(|s: Stored<_>| s.data).unwrap_or_default();
+
+    if !offline {
+        match fetch.await {
+            Ok(guid) => {
+                if guids.get(&key) != Some(&guid) {
+                    guids.insert(key, guid.clone());
+                    if let Some(path) = guids_path() {
+                        save(path, &guids).ok();
+                    }
+                }
+                return Ok(guid);
+            },
+            Err(e) => {
+                if !guids.contains_key(&key) {
+                    return Err(failed(e));
+                }
+            },
+        }
+    }
+
+    guids.remove(&key).ok_or_else(|| not_stored(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_weeks_of_the_calendar_year_are_fetchable() {
+        let date = |y, m, d| NaiveDate::from_ymd(y, m, d);
+        assert!(fetchable_on(Week { year: 2021, week: 10 }, date(2021, 3, 10)));
+        // 2021-01-01 is in 2020-W53, which libschedule24 would fetch from 2021
+        assert!(!fetchable_on(Week::of(date(2021, 1, 1)), date(2021, 1, 1)));
+        assert!(fetchable_on(Week { year: 2021, week: 1 }, date(2021, 1, 1)));
+        // 2024-12-30 is in 2025-W01, which libschedule24 would fetch from 2024
+        assert!(!fetchable_on(Week::of(date(2024, 12, 30)), date(2024, 12, 30)));
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     io,
     error::Error,
+    panic,
     thread,
     sync::mpsc,
     time::Duration,
@@ -10,7 +11,7 @@ use tui::{
     Terminal,
     //backend::TermionBackend,
     backend::CrosstermBackend,
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
     layout::{Layout, Constraint, Direction, Rect},
     text::{Span, Spans},
     style::{Style, Color},
@@ -33,19 +34,51 @@ use libschedule24::data;
 
 
 
-use chrono::NaiveTime;
+use chrono::{Local, Datelike, NaiveTime};
 use substring::Substring;
 
+use crate::{date::Week, lesson::{self, DAY_NAMES}, theme::Palette};
+
+
 enum Event<I> {
     Key(I),
     Tick
 }
 
-pub fn run(lesson_info: &[Vec<data::LessonInfo>]) -> Result<(), Box<dyn Error>> {
+pub fn run<F>(lesson_info: &[Vec<data::LessonInfo>], classes: &[String], week: Week, day: Option<i32>, palette: &Palette, fetch: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(Week, Option<i32>) -> Result<Vec<Vec<data::LessonInfo>>, String>,
+{
     //let stdout = io::stdout().into_raw_mode()?;
     //let stdout = AlternateScreen::from(stdout);
     execute!(io::stdout(), EnterAlternateScreen)?;
     enable_raw_mode()?;
+
+    // Give the terminal back before a panic message is printed, otherwise it
+    // is left in raw mode
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal().ok();
+        hook(info);
+    }));
+
+    let result = run_app(lesson_info, classes, week, day, palette, fetch);
+    restore_terminal()?;
+    // Back to the default hook now that the terminal is restored
+    let _ = panic::take_hook();
+    result
+}
+
+fn restore_terminal() -> Result<(), Box<dyn Error>> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    Ok(())
+}
+
+fn run_app<F>(lesson_info: &[Vec<data::LessonInfo>], classes: &[String], mut week: Week, mut day: Option<i32>, palette: &Palette, mut fetch: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(Week, Option<i32>) -> Result<Vec<Vec<data::LessonInfo>>, String>,
+{
     //let backend = TermionBackend::new(stdout);
     let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
@@ -54,36 +87,26 @@ pub fn run(lesson_info: &[Vec<data::LessonInfo>]) -> Result<(), Box<dyn Error>>
 
     spawn_threads(tx);
 
-    let mut sorted_lessons: Vec<Vec<data::LessonInfo>> = Vec::new();
-    for lesson in lesson_info {
-        sorted_lessons.push(sort_lessons(lesson)?);
-    }
+    let (mut sorted_lessons, mut lesson_constraints, mut same_time_lessons, mut bounds) = prepare_lessons(lesson_info)?;
 
-    let mut first_lesson = NaiveTime::from_hms(23,59,59);
-    let mut last_lesson = NaiveTime::from_hms(0,0,0);
-
-    for lesson in &sorted_lessons {
-        if lesson.is_empty() {continue;}
-        let first_time = NaiveTime::parse_from_str(&lesson[0].time_start.to_string(), "%H:%M:%S").expect("Failed to parse time!");
-        let last_time = NaiveTime::parse_from_str(&lesson[lesson.len() - 1].time_end.to_string(), "%H:%M:%S").expect("Failed to parse time!");
-        if first_time < first_lesson {
-            first_lesson = first_time;
-        }
-        if last_time > last_lesson {
-            last_lesson = last_time;
-        }
-    }
-
-    let mut lesson_constraints: Vec<Vec<Constraint>> = Vec::new();
-    let mut same_time_lessons: Vec<Vec<i32>> = Vec::new();
-    for lesson in &sorted_lessons {
-        let (constraints, same_times) = generate_constraints(lesson, first_lesson, last_lesson)?;
-        lesson_constraints.push(constraints);
-        same_time_lessons.push(same_times);
-    }
+    let mut fetch_error = None;
+    let mut today = Local::now().date().naive_local();
+    let mut today_lessons = fetch_today(&mut fetch, &mut fetch_error)?;
+    let mut selected: (usize, usize) = clamp_selection((0, 0), &sorted_lessons);
+    let mut show_details = false;
 
     loop {
         terminal.draw(|f| {
+            let title = match day {
+                Some(day) => format!(" Week {}, {} - {} ", week.week, week.year, DAY_NAMES[(day as usize).saturating_sub(1) % 5]),
+                None => format!(" Week {}, {} ", week.week, week.year),
+            };
+            let mut title = vec![Span::raw(title)];
+            if let Some(message) = &fetch_error {
+                title.push(Span::styled(format!("{} ", message), Style::default().fg(palette.error)));
+            }
+            f.render_widget(Block::default().title(Spans::from(title)), f.size());
+
             let mut constraints: Vec<Constraint> = Vec::new();
             for _ in 0..sorted_lessons.len() {
                 constraints.push(Constraint::Percentage(100 / sorted_lessons.len() as u16));
@@ -95,8 +118,15 @@ pub fn run(lesson_info: &[Vec<data::LessonInfo>]) -> Result<(), Box<dyn Error>>
                 .split(f.size());
 
             for j in 0..sorted_lessons.len() {
-                let block = Block::default()
+                let mut block = Block::default()
                     .borders(Borders::ALL);
+                if classes.len() > 1 {
+                    let title = match day {
+                        Some(_) => format!(" {} ", classes[j % classes.len()]),
+                        None => format!(" {} {} ", &DAY_NAMES[(j / classes.len()) % 5][..3], classes[j % classes.len()]),
+                    };
+                    block = block.title(title);
+                }
                 f.render_widget(block, chunks[j]);
 
                 let chunks = Layout::default()
@@ -130,20 +160,22 @@ pub fn run(lesson_info: &[Vec<data::LessonInfo>]) -> Result<(), Box<dyn Error>>
                             .split(chunks[i])
                     };
 
-                    let color = sorted_lessons[j][sorted_i].block.b_color.to_string();
-                    let color = Color::Rgb(u8::from_str_radix(color.substring(1,3), 16).ok().unwrap(), u8::from_str_radix(color.substring(3,5), 16).ok().unwrap(), u8::from_str_radix(color.substring(5,7), 16).ok().unwrap());
+                    let b_color = lesson_color(&sorted_lessons[j][sorted_i]);
+                    let color = palette.lesson(b_color);
+                    let border_type = if selected == (j, sorted_i) { BorderType::Double } else { BorderType::Plain };
 
                     let time = sorted_lessons[j][sorted_i].time_start.to_string();
                     let text = Spans::from(vec![
                         Span::raw("─"),
-                        Span::styled(time.substring(0, 5), Style::default().fg(Color::Green)),
+                        Span::styled(time.substring(0, 5), Style::default().fg(palette.start)),
                         Span::raw(" - "),
-                        Span::styled(sorted_lessons[j][sorted_i].texts[0].to_string(), Style::default().fg(color)),
+                        Span::styled(lesson::text(&sorted_lessons[j][sorted_i], 0), Style::default().fg(color)),
                     ]);
 
                     let block = Block::default()
                         .title(text)
                         .borders(Borders::ALL)
+                        .border_type(border_type)
                         .border_style(Style::default().fg(color).bg(Color::Reset))
                         .style(Style::default().bg(color));
                     f.render_widget(block, chunks[skip_current as usize]);
@@ -160,11 +192,11 @@ pub fn run(lesson_info: &[Vec<data::LessonInfo>]) -> Result<(), Box<dyn Error>>
                         let text = vec![
                             Spans::from(vec![
                                 Span::raw(" "),
-                                Span::styled(sorted_lessons[j][sorted_i].texts[0].to_string(), Style::default().bg(color).fg(Color::Black))
+                                Span::styled(lesson::text(&sorted_lessons[j][sorted_i], 0), Style::default().bg(color).fg(palette.text(b_color)))
                             ]),
                             Spans::from(vec![
                                 Span::raw(" "),
-                                Span::styled(sorted_lessons[j][sorted_i].texts[2].to_string(), Style::default().bg(color).fg(Color::Black)),
+                                Span::styled(lesson::text(&sorted_lessons[j][sorted_i], 2), Style::default().bg(color).fg(palette.text(b_color))),
                             ])
                         ];
 
@@ -184,9 +216,9 @@ pub fn run(lesson_info: &[Vec<data::LessonInfo>]) -> Result<(), Box<dyn Error>>
                     let time = sorted_lessons[j][sorted_i].time_end.to_string();
                     let text = Spans::from(vec![
                         Span::raw("└─"),
-                        Span::styled(time.substring(0, 5), Style::default().fg(Color::Red)),
+                        Span::styled(time.substring(0, 5), Style::default().fg(palette.end)),
                         Span::raw(" - "),
-                        Span::styled(sorted_lessons[j][sorted_i].texts[2].to_string(), Style::default().fg(color)),
+                        Span::styled(lesson::text(&sorted_lessons[j][sorted_i], 2), Style::default().fg(color)),
                     ]);
                     let block = Paragraph::new(text);
                     f.render_widget(block, chunks[1]);
@@ -202,14 +234,76 @@ pub fn run(lesson_info: &[Vec<data::LessonInfo>]) -> Result<(), Box<dyn Error>>
                     sorted_i += 1;
                 }
             }
+
+            let now = Local::now();
+            if Week::of(now.date().naive_local()) == week {
+                for (j, chunk) in chunks.iter().enumerate() {
+                    let column_day = day.unwrap_or((j / classes.len().max(1)) as i32 + 1);
+                    if column_day != now.weekday().number_from_monday() as i32 {
+                        continue;
+                    }
+                    if let Some(area) = now_line(*chunk, bounds, now.time()) {
+                        let line = "━".repeat(area.width as usize);
+                        f.render_widget(Paragraph::new(Span::styled(line, Style::default().fg(palette.now))), area);
+                    }
+                }
+            }
+
+            let size = f.size();
+            if size.height > 0 {
+                let area = Rect { x: size.x, y: size.y + size.height - 1, width: size.width, height: 1 };
+                f.render_widget(Paragraph::new(status_line(&today_lessons, now.time(), palette)), area);
+            }
+
+            if show_details {
+                if let Some(lesson) = sorted_lessons.get(selected.0).and_then(|l| l.get(selected.1)) {
+                    let area = centered_rect(60, 50, f.size());
+                    f.render_widget(Clear, area);
+                    f.render_widget(lesson_details(lesson, palette), area);
+                }
+            }
         })?;
 
         let evt = rx.recv()?;
+        if let Event::Tick = evt {
+            // The status line shows the lessons of the new day after midnight
+            let date = Local::now().date().naive_local();
+            if date != today {
+                today = date;
+                today_lessons = fetch_today(&mut fetch, &mut fetch_error)?;
+            }
+        }
         if let Event::Key(key) = evt {
-            if let KeyCode::Char('q') = key.code {
-                disable_raw_mode()?;
-                execute!(io::stdout(), LeaveAlternateScreen)?;
-                break
+            let mut navigate = None;
+            match key.code {
+                KeyCode::Char('q') => break,
+                KeyCode::Esc => show_details = false,
+                KeyCode::Enter => show_details = !show_details && sorted_lessons.get(selected.0).and_then(|l| l.get(selected.1)).is_some(),
+                KeyCode::Up | KeyCode::Char('k') => selected.1 = selected.1.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => selected = clamp_selection((selected.0, selected.1 + 1), &sorted_lessons),
+                KeyCode::Tab => selected = clamp_selection(((selected.0 + 1) % sorted_lessons.len().max(1), selected.1), &sorted_lessons),
+                KeyCode::BackTab => selected = clamp_selection((selected.0.checked_sub(1).unwrap_or(sorted_lessons.len().saturating_sub(1)), selected.1), &sorted_lessons),
+                KeyCode::Left | KeyCode::Char('h') => navigate = Some(step(week, day, -1)),
+                KeyCode::Right | KeyCode::Char('l') => navigate = Some(step(week, day, 1)),
+                _ => {},
+            }
+
+            if let Some((new_week, new_day)) = navigate {
+                match fetch(new_week, new_day) {
+                    Ok(lesson_info) => {
+                        let (sorted, constraints, same_times, new_bounds) = prepare_lessons(&lesson_info)?;
+                        sorted_lessons = sorted;
+                        bounds = new_bounds;
+                        lesson_constraints = constraints;
+                        same_time_lessons = same_times;
+                        week = new_week;
+                        day = new_day;
+                        fetch_error = None;
+                        selected = clamp_selection(selected, &sorted_lessons);
+                        show_details = false;
+                    },
+                    Err(message) => fetch_error = Some(message),
+                }
             }
         }
     }
@@ -217,24 +311,194 @@ pub fn run(lesson_info: &[Vec<data::LessonInfo>]) -> Result<(), Box<dyn Error>>
     Ok(())
 }
 
-fn sort_lessons(lesson_info: &[data::LessonInfo]) -> Result<Vec<data::LessonInfo>, Box<dyn Error>> {
-    let mut sorted = Vec::new();
-    let mut lessons = lesson_info.to_vec();
-
-    for _ in 0..lesson_info.len() {
-        let mut first = NaiveTime::from_hms(23,59,59);
-        let mut first_val = 0;
-
-        for (i, lesson) in lessons.iter().enumerate() {
-            let time = NaiveTime::parse_from_str(&lesson.time_start.to_string(), "%H:%M:%S").expect("Failed to parse time!");
-            if time < first {
-                first = time;
-                first_val = i;
-            }
+fn fetch_today<F>(fetch: &mut F, fetch_error: &mut Option<String>) -> Result<Vec<data::LessonInfo>, Box<dyn Error>>
+where
+    F: FnMut(Week, Option<i32>) -> Result<Vec<Vec<data::LessonInfo>>, String>,
+{
+    let today = Local::now().weekday().number_from_monday() as i32;
+    if today > 5 {
+        return Ok(Vec::new());
+    }
+    match fetch(Week::current(), Some(today)) {
+        Ok(lesson_info) => sort_lessons(&lesson_info.into_iter().next().unwrap_or_default()),
+        Err(message) => {
+            *fetch_error = Some(message);
+            Ok(Vec::new())
+        },
+    }
+}
+
+fn clamp_selection(selected: (usize, usize), sorted_lessons: &[Vec<data::LessonInfo>]) -> (usize, usize) {
+    let column = selected.0.min(sorted_lessons.len().saturating_sub(1));
+    let row = match sorted_lessons.get(column) {
+        Some(lessons) => selected.1.min(lessons.len().saturating_sub(1)),
+        None => 0,
+    };
+    (column, row)
+}
+
+fn lesson_color(lesson: &data::LessonInfo) -> Color {
+    let (r, g, b) = lesson::color(lesson);
+    Color::Rgb(r, g, b)
+}
+
+fn lesson_details<'a>(lesson: &'a data::LessonInfo, palette: &Palette) -> Paragraph<'a> {
+    let time = |time: Result<NaiveTime, lesson::ParseError>| match time {
+        Ok(time) => time.format("%H:%M:%S").to_string(),
+        Err(e) => e.to_string(),
+    };
+    let duration = match lesson::times(lesson) {
+        Ok((time_start, time_end)) => format!("{} min", time_end.signed_duration_since(time_start).num_minutes()),
+        Err(_) => "?".to_string(),
+    };
+
+    let mut text = vec![
+        Spans::from(vec![
+            Span::raw("Start:    "),
+            Span::styled(time(lesson::start(lesson)), Style::default().fg(palette.start)),
+        ]),
+        Spans::from(vec![
+            Span::raw("End:      "),
+            Span::styled(time(lesson::end(lesson)), Style::default().fg(palette.end)),
+        ]),
+        Spans::from(vec![
+            Span::raw("Duration: "),
+            Span::raw(duration),
+        ]),
+        Spans::from(vec![
+            Span::raw("Colour:   "),
+            Span::styled("  ", Style::default().bg(palette.color(lesson_color(lesson)))),
+            Span::raw(format!(" {}", lesson.block.b_color)),
+        ]),
+        Spans::from(""),
+    ];
+    for (i, t) in lesson.texts.iter().enumerate() {
+        text.push(Spans::from(format!("{}: {}", i, t)));
+    }
+
+    let title = lesson::text(lesson, 0);
+    Paragraph::new(text)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .wrap(Wrap { trim: false })
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
+
+type PreparedLessons = (Vec<Vec<data::LessonInfo>>, Vec<Vec<Constraint>>, Vec<Vec<i32>>, (NaiveTime, NaiveTime));
+
+fn prepare_lessons(lesson_info: &[Vec<data::LessonInfo>]) -> Result<PreparedLessons, Box<dyn Error>> {
+    let mut sorted_lessons: Vec<Vec<data::LessonInfo>> = Vec::new();
+    for lesson in lesson_info {
+        sorted_lessons.push(sort_lessons(lesson)?);
+    }
+
+    let mut first_lesson = NaiveTime::from_hms(23,59,59);
+    let mut last_lesson = NaiveTime::from_hms(0,0,0);
+
+    for lesson in &sorted_lessons {
+        if lesson.is_empty() {continue;}
+        let first_time = lesson::start(&lesson[0])?;
+        let last_time = lesson::end(&lesson[lesson.len() - 1])?;
+        if first_time < first_lesson {
+            first_lesson = first_time;
+        }
+        if last_time > last_lesson {
+            last_lesson = last_time;
+        }
+    }
+
+    let mut lesson_constraints: Vec<Vec<Constraint>> = Vec::new();
+    let mut same_time_lessons: Vec<Vec<i32>> = Vec::new();
+    for lesson in &sorted_lessons {
+        let (constraints, same_times) = generate_constraints(lesson, first_lesson, last_lesson)?;
+        lesson_constraints.push(constraints);
+        same_time_lessons.push(same_times);
+    }
+
+    Ok((sorted_lessons, lesson_constraints, same_time_lessons, (first_lesson, last_lesson)))
+}
+
+/// Row inside a day column that corresponds to `time`, if the time is within
+/// the displayed part of the day.
+fn now_line(column: Rect, (first_lesson, last_lesson): (NaiveTime, NaiveTime), time: NaiveTime) -> Option<Rect> {
+    if time < first_lesson || time > last_lesson || column.width < 2 || column.height < 3 {
+        return None;
+    }
+    let day = last_lesson.signed_duration_since(first_lesson).num_seconds();
+    let elapsed = time.signed_duration_since(first_lesson).num_seconds();
+    if day <= 0 {
+        return None;
+    }
+
+    let inner_height = column.height - 2;
+    let offset = ((elapsed * inner_height as i64) / day).min(inner_height as i64 - 1) as u16;
+    Some(Rect { x: column.x + 1, y: column.y + 1 + offset, width: column.width - 2, height: 1 })
+}
+
+fn status_line(lessons: &[data::LessonInfo], time: NaiveTime, palette: &Palette) -> Spans<'static> {
+    let mut spans = vec![Span::raw(format!(" {} ", time.format("%H:%M:%S")))];
+
+    for (lesson, (time_start, time_end)) in lessons.iter().filter_map(|lesson| Some((lesson, lesson::times(lesson).ok()?))) {
+        if time_start <= time && time < time_end {
+            spans.push(Span::raw("│ Now: "));
+            spans.push(Span::styled(lesson::text(lesson, 0), Style::default().fg(palette.current)));
+            spans.push(Span::raw(format!(", {} left ", lesson::format_duration(time_end.signed_duration_since(time)))));
+            break;
         }
+    }
+
+    match lessons.iter().filter_map(|lesson| Some((lesson, lesson::start(lesson).ok()?))).find(|(_, time_start)| *time_start > time) {
+        Some((lesson, time_start)) => {
+            spans.push(Span::raw("│ Next: "));
+            spans.push(Span::styled(lesson::text(lesson, 0), Style::default().fg(palette.next)));
+            spans.push(Span::raw(format!(" at {}, in {} ", time_start.format("%H:%M"), lesson::format_duration(time_start.signed_duration_since(time)))));
+        },
+        None => spans.push(Span::raw("│ No more lessons today ")),
+    }
 
-        sorted.push(lessons.remove(first_val));
+    Spans::from(spans)
+}
+
+/// Moves the view one week, or one school day when a single day is shown.
+fn step(week: Week, day: Option<i32>, direction: i32) -> (Week, Option<i32>) {
+    match day {
+        None => (week.offset(direction as i64), None),
+        Some(day) => {
+            let day = day + direction;
+            if day < 1 {
+                (week.offset(-1), Some(5))
+            } else if day > 5 {
+                (week.offset(1), Some(1))
+            } else {
+                (week, Some(day))
+            }
+        }
     }
+}
+
+// Lessons with malformed times are left out, so the rest of the TUI can
+// rely on the times of the sorted lessons
+fn sort_lessons(lesson_info: &[data::LessonInfo]) -> Result<Vec<data::LessonInfo>, Box<dyn Error>> {
+    let mut sorted = lesson::valid(lesson_info);
+    sorted.sort_by_key(|lesson| lesson::start(lesson).ok());
 
     Ok(sorted)
 }
@@ -250,8 +514,7 @@ fn generate_constraints(lesson_info: &[data::LessonInfo], first_lesson: NaiveTim
     let day = end_of_day.signed_duration_since(first_lesson).num_minutes();
 
     for (i, lesson) in lesson_info.iter().enumerate() {
-        let time_start = NaiveTime::parse_from_str(&lesson.time_start.to_string(), "%H:%M:%S").expect("Failed to parse time!");
-        let time_end = NaiveTime::parse_from_str(&lesson.time_end.to_string(), "%H:%M:%S").expect("Failed to parse time!");
+        let (time_start, time_end) = lesson::times(lesson)?;
 
         if (last_lesson_end == time_end || last_lesson_start == time_start) && i != 0 {
             same_times.push(i as i32 - 1);
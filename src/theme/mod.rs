@@ -0,0 +1,334 @@
+use tui::style::Color;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{lesson, wallpaper};
+
+pub const THEMES: [&str; 4] = ["dark", "light", "monochrome", "high-contrast"];
+
+/// The `[theme]` section of the config file. `base` picks one of the built-in
+/// themes and every other field overrides a single colour of it.
+///
+/// Colours are names like `green` or `lightred`, `#rrggbb` or `reset` for the
+/// terminal default. `lesson` can also be `skola24` to use the colours from
+/// the schedule and `text` can be `auto` to pick black or white depending on
+/// the lesson colour.
+///
+/// `colors` is `truecolor`, `256` or `16` for terminals that are detected
+/// wrong, by default it is worked out from `COLORTERM` and `TERM`.
+///
+/// The wallpaper uses `wallpaper_background` and `wallpaper_foreground`, and
+/// highlights the day it shows with `current`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Theme {
+    pub base: String,
+    pub colors: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub text: Option<String>,
+    pub lesson: Option<String>,
+    pub now: Option<String>,
+    pub current: Option<String>,
+    pub next: Option<String>,
+    pub error: Option<String>,
+    pub wallpaper_background: Option<String>,
+    pub wallpaper_foreground: Option<String>,
+}
+
+/// How many colours the terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    pub fn detect() -> ColorDepth {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default().to_lowercase();
+        let term = std::env::var("TERM").unwrap_or_default().to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    fn parse(value: &str) -> Result<Option<ColorDepth>, String> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(None),
+            "truecolor" | "24bit" => Ok(Some(ColorDepth::TrueColor)),
+            "256" => Ok(Some(ColorDepth::Ansi256)),
+            "16" => Ok(Some(ColorDepth::Ansi16)),
+            _ => Err(format!("Unknown colors \"{}\" in the theme, use auto, truecolor, 256 or 16", value)),
+        }
+    }
+
+    /// The closest colour the terminal can show.
+    pub fn convert(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(ansi256(r, g, b)),
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => ansi16(r, g, b),
+            (_, color) => color,
+        }
+    }
+}
+
+/// Colours used by the TUI.
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    pub start: Color,
+    pub end: Color,
+    /// Text on lessons, `None` picks black or white for every lesson
+    pub text: Option<Color>,
+    /// Lesson background, `None` uses the colour from Skola24
+    pub lesson: Option<Color>,
+    pub now: Color,
+    pub current: Color,
+    pub next: Color,
+    pub error: Color,
+    pub depth: ColorDepth,
+}
+
+impl Palette {
+    pub fn builtin(name: &str) -> Option<Palette> {
+        match name {
+            "" | "dark" => Some(Palette {
+                start: Color::Green,
+                end: Color::Red,
+                text: Some(Color::Black),
+                lesson: None,
+                now: Color::Yellow,
+                current: Color::Yellow,
+                next: Color::Green,
+                error: Color::Red,
+                depth: ColorDepth::TrueColor,
+            }),
+            "light" => Some(Palette {
+                start: Color::Green,
+                end: Color::Red,
+                text: Some(Color::Black),
+                lesson: None,
+                now: Color::Magenta,
+                current: Color::Blue,
+                next: Color::Green,
+                error: Color::Red,
+                depth: ColorDepth::TrueColor,
+            }),
+            "monochrome" => Some(Palette {
+                start: Color::Reset,
+                end: Color::Reset,
+                text: Some(Color::Reset),
+                lesson: Some(Color::Reset),
+                now: Color::Reset,
+                current: Color::Reset,
+                next: Color::Reset,
+                error: Color::Reset,
+                depth: ColorDepth::TrueColor,
+            }),
+            "high-contrast" => Some(Palette {
+                start: Color::LightGreen,
+                end: Color::LightRed,
+                text: Some(Color::White),
+                lesson: Some(Color::Black),
+                now: Color::LightYellow,
+                current: Color::LightYellow,
+                next: Color::LightGreen,
+                error: Color::LightRed,
+                depth: ColorDepth::TrueColor,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Background of a lesson that Skola24 gave `color`.
+    pub fn lesson(&self, color: Color) -> Color {
+        self.color(self.lesson.unwrap_or(color))
+    }
+
+    /// Text colour on top of a lesson that Skola24 gave `color`.
+    pub fn text(&self, color: Color) -> Color {
+        match (self.text, self.lesson.unwrap_or(color)) {
+            (Some(color), _) => self.color(color),
+            (None, Color::Rgb(r, g, b)) => {
+                let luma = 299 * r as u32 + 587 * g as u32 + 114 * b as u32;
+                if luma > 128_000 { Color::Black } else { Color::White }
+            },
+            (None, _) => Color::Reset,
+        }
+    }
+
+    /// `color` as close as the terminal can show it.
+    pub fn color(&self, color: Color) -> Color {
+        self.depth.convert(color)
+    }
+}
+
+impl Theme {
+    /// Builds the palette from the base theme and the overrides. `base`
+    /// replaces the configured base theme when given.
+    pub fn palette(&self, base: Option<&str>) -> Result<Palette, String> {
+        let base = base.unwrap_or(&self.base);
+        let mut palette = Palette::builtin(base)
+            .ok_or_else(|| format!("Unknown theme \"{}\", the built-in themes are {}", base, THEMES.join(", ")))?;
+
+        for (value, color) in [
+            (&self.start, &mut palette.start),
+            (&self.end, &mut palette.end),
+            (&self.now, &mut palette.now),
+            (&self.current, &mut palette.current),
+            (&self.next, &mut palette.next),
+            (&self.error, &mut palette.error),
+        ] {
+            if let Some(value) = value {
+                *color = parse(value)?;
+            }
+        }
+        match self.text.as_deref() {
+            Some("auto") => palette.text = None,
+            Some(value) => palette.text = Some(parse(value)?),
+            None => {},
+        }
+        match self.lesson.as_deref() {
+            Some("skola24") => palette.lesson = None,
+            Some(value) => palette.lesson = Some(parse(value)?),
+            None => {},
+        }
+
+        palette.depth = match self.colors.as_deref().map(ColorDepth::parse).transpose()?.flatten() {
+            Some(depth) => depth,
+            None => ColorDepth::detect(),
+        };
+        for color in [
+            &mut palette.start,
+            &mut palette.end,
+            &mut palette.now,
+            &mut palette.current,
+            &mut palette.next,
+            &mut palette.error,
+        ] {
+            *color = palette.depth.convert(*color);
+        }
+
+        Ok(palette)
+    }
+
+    /// Colours of the wallpaper, always in true colour since it is an image.
+    pub fn wallpaper(&self, base: Option<&str>) -> Result<wallpaper::Colors, String> {
+        let base = base.unwrap_or(&self.base);
+        let palette = Palette::builtin(base)
+            .ok_or_else(|| format!("Unknown theme \"{}\", the built-in themes are {}", base, THEMES.join(", ")))?;
+        let (background, foreground) = match base {
+            "light" => (Color::Rgb(0xfa, 0xfa, 0xfa), Color::Rgb(0x20, 0x20, 0x20)),
+            "monochrome" | "high-contrast" => (Color::Black, Color::White),
+            _ => (Color::Rgb(0x1e, 0x1e, 0x2e), Color::Rgb(0xf0, 0xf0, 0xf0)),
+        };
+
+        let color = |value: &Option<String>, default: Color| match value {
+            Some(value) => parse(value),
+            None => Ok(default),
+        };
+        let background = color(&self.wallpaper_background, background)?;
+        let foreground = color(&self.wallpaper_foreground, foreground)?;
+        let highlight = color(&self.current, palette.current)?;
+
+        Ok(wallpaper::Colors {
+            background: hex(background, (0, 0, 0)),
+            foreground: hex(foreground, (255, 255, 255)),
+            highlight: hex(highlight, rgb(foreground).unwrap_or((255, 255, 255))),
+        })
+    }
+}
+
+// `reset` has no colour of its own, so it becomes `default`
+fn hex(color: Color, default: (u8, u8, u8)) -> String {
+    lesson::hex(rgb(color).unwrap_or(default))
+}
+
+fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+    match color {
+        Color::Rgb(r, g, b) => Some((r, g, b)),
+        color => ANSI16.iter().find(|(c, _)| *c == color).map(|(_, rgb)| *rgb),
+    }
+}
+
+fn parse(value: &str) -> Result<Color, String> {
+    let color = match value.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex => match lesson::parse_color(hex) {
+            Ok((r, g, b)) => Color::Rgb(r, g, b),
+            Err(_) => return Err(format!("Unknown colour \"{}\" in the theme", value)),
+        },
+    };
+    Ok(color)
+}
+
+// The 6x6x6 colour cube and the grey ramp of the 256 colour palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| (0..CUBE_LEVELS.len()).min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs()).unwrap_or(0);
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let grey_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let grey = 8 + grey_index * 10;
+
+    if distance((r, g, b), (grey, grey, grey)) < distance((r, g, b), cube) {
+        232 + grey_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+// The colours xterm uses for the 16 standard colours
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16.iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
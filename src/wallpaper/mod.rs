@@ -0,0 +1,67 @@
+const HEADER_HEIGHT: u32 = 80;
+const STRIPE_HEIGHT: u32 = 6;
+
+/// Colours of the wallpaper as `#rrggbb`, see `Theme::wallpaper`.
+pub struct Colors {
+    pub background: String,
+    pub foreground: String,
+    /// The label of the day that is shown and a stripe below the header
+    pub highlight: String,
+}
+
+/// Size of the schedule image below the header, as a resolution that
+/// `Dimensions` can parse.
+pub fn schedule_resolution(resolution: &str) -> Option<String> {
+    let (width, height) = size(resolution)?;
+    Some(format!("{}x{}", width, height.checked_sub(HEADER_HEIGHT)?.max(1)))
+}
+
+/// Puts the schedule SVG from `image::generate_svg` below a header with
+/// `label` highlighted followed by `title`, filling the whole resolution.
+pub fn compose(schedule: &str, resolution: &str, label: &str, title: &str, colors: &Colors) -> Option<String> {
+    let (width, height) = size(resolution)?;
+
+    let schedule = schedule.trim_start();
+    let schedule = match schedule.strip_prefix("<?xml") {
+        Some(rest) => rest.split_once("?>")?.1.trim_start(),
+        None => schedule,
+    };
+    // The schedule keeps its own size and view box, it is only moved below
+    // the header
+    let schedule = schedule.strip_prefix("<svg")?;
+
+    Some(format!(
+        concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">",
+            "<rect width=\"{width}\" height=\"{height}\" fill=\"{background}\"/>",
+            "<text x=\"{margin}\" y=\"{baseline}\" font-family=\"sans-serif\" font-size=\"{font_size}\" fill=\"{foreground}\">",
+            "<tspan fill=\"{highlight}\" font-weight=\"bold\">{label}</tspan> {title}</text>",
+            "<rect y=\"{stripe}\" width=\"{width}\" height=\"{stripe_height}\" fill=\"{highlight}\"/>",
+            "<svg x=\"0\" y=\"{header}\"{schedule}",
+            "</svg>",
+        ),
+        width = width,
+        height = height,
+        background = colors.background,
+        foreground = colors.foreground,
+        highlight = colors.highlight,
+        margin = HEADER_HEIGHT / 3,
+        baseline = HEADER_HEIGHT * 2 / 3,
+        font_size = HEADER_HEIGHT / 2,
+        label = escape(label),
+        title = escape(title),
+        stripe = HEADER_HEIGHT - STRIPE_HEIGHT,
+        stripe_height = STRIPE_HEIGHT,
+        header = HEADER_HEIGHT,
+        schedule = schedule,
+    ))
+}
+
+fn size(resolution: &str) -> Option<(u32, u32)> {
+    let (width, height) = resolution.split_once('x')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}